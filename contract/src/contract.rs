@@ -1,11 +1,13 @@
 use model::{
     code_hash::CodeHash,
-    verification::{Verification, VerificationRequest, VerificationStatus},
+    verification::{
+        BuildLog, Verification, VerificationFailure, VerificationRequest, VerificationStatus,
+    },
 };
 use near_sdk::{
     assert_one_yocto,
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::{UnorderedMap, Vector},
+    collections::{LookupMap, UnorderedMap, Vector},
     env,
     json_types::{U128, U64},
     near_bindgen, require, AccountId, BorshStorageKey, PanicOnDefault,
@@ -18,10 +20,12 @@ use crate::{
 };
 
 #[derive(BorshStorageKey, BorshSerialize)]
-enum StorageKey {
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum StorageKey {
     OWNERSHIP,
     REQUESTS,
     VERIFICATIONS,
+    FAILURES,
 }
 
 #[near_bindgen]
@@ -67,6 +71,10 @@ impl Contract {
             .find(|v| v.request_id == request_id)
     }
 
+    pub fn get_verification_failure(&self, request_id: U64) -> Option<VerificationFailure> {
+        Self::failures().get(&request_id.into())
+    }
+
     pub fn verify_code_hash(&self, code_hash: CodeHash) -> Option<Verification> {
         self.verifications.get(&code_hash)
     }
@@ -100,6 +108,7 @@ impl Contract {
 
         let request = VerificationRequest {
             id,
+            requester: Some(env::predecessor_account_id()),
             repository,
             checkout,
             path,
@@ -116,7 +125,7 @@ impl Contract {
         request
     }
 
-    fn resolve(&mut self, id: u64, result: Option<Verification>) {
+    fn resolve(&mut self, id: u64, result: Result<Verification, Option<BuildLog>>) {
         let attached_deposit = env::attached_deposit();
         require!(attached_deposit > 0, "Deposit required");
 
@@ -135,38 +144,50 @@ impl Contract {
         );
 
         let now = env::block_timestamp();
-        if let Some(result) = &result {
-            self.verifications.insert(&result.code_hash, &result);
-            self.requests.replace(
-                id,
-                &VerificationRequest {
-                    status: VerificationStatus::SUCCESS,
-                    updated_at: now,
-                    ..request
-                },
-            );
-        } else {
-            self.requests.replace(
-                id,
-                &VerificationRequest {
-                    status: VerificationStatus::FAILURE,
-                    updated_at: now,
-                    ..request
-                },
-            );
-        }
+        let status = match result {
+            Ok(result) => {
+                self.verifications.insert(&result.code_hash, &result);
+                VerificationStatus::SUCCESS
+            }
+            Err(log) => {
+                Self::failures().insert(
+                    &id,
+                    &VerificationFailure {
+                        request_id: id,
+                        log,
+                    },
+                );
+                VerificationStatus::FAILURE
+            }
+        };
+        self.requests.replace(
+            id,
+            &VerificationRequest {
+                status,
+                updated_at: now,
+                ..request
+            },
+        );
 
         storage_refund(storage_usage_start, 0);
     }
 
     #[payable]
     pub fn verification_success(&mut self, result: Verification) {
-        self.resolve(result.request_id, Some(result));
+        self.resolve(result.request_id, Ok(result));
     }
 
     #[payable]
-    pub fn verification_failure(&mut self, id: U64) {
-        self.resolve(id.into(), None);
+    pub fn verification_failure(&mut self, id: U64, log: Option<BuildLog>) {
+        self.resolve(id.into(), Err(log));
+    }
+}
+
+impl Contract {
+    /// Failures are kept out of the root state, so that recording them did
+    /// not change the layout of contracts deployed before.
+    fn failures() -> LookupMap<u64, VerificationFailure> {
+        LookupMap::new(StorageKey::FAILURES)
     }
 }
 
@@ -1,12 +1,13 @@
-mod ownership;
+pub mod ownership;
 mod utils;
 
 mod contract;
+mod migration;
 pub use contract::*;
 
 #[cfg(test)]
 mod tests {
-    use model::verification::VerificationStatus;
+    use model::verification::{BuildLog, VerificationFailure, VerificationStatus};
     use near_sdk::{test_utils::*, testing_env, AccountId};
 
     use crate::{ownership::Ownable, Contract};
@@ -29,10 +30,10 @@ mod tests {
         "bob".parse::<AccountId>().unwrap()
     }
 
-    const VERIFICATION_FEE: u128 = ONE_NEAR * 1;
-    const REPOSITORY_URL: &'static str = "https://github.com/NEAR-Edu/stats.gallery-dapp.git";
-    const CHECKOUT: &'static str = "main";
-    const PATH: &'static str = "";
+    const VERIFICATION_FEE: u128 = ONE_NEAR;
+    const REPOSITORY_URL: &str = "https://github.com/NEAR-Edu/stats.gallery-dapp.git";
+    const CHECKOUT: &str = "main";
+    const PATH: &str = "";
 
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
@@ -70,6 +71,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transfer_ownership() {
+        let mut context = get_context(account_owner());
+        context.attached_deposit(1);
+        testing_env!(context.build());
+
+        let mut contract = Contract::new(account_owner(), VERIFICATION_FEE.into());
+        contract.own_propose_owner(Some(account_user2()));
+        assert_eq!(contract.own_get_proposed_owner(), Some(account_user2()));
+
+        let mut context = get_context(account_user2());
+        context.attached_deposit(1);
+        testing_env!(context.build());
+
+        contract.own_accept_owner();
+        assert_eq!(contract.own_get_owner(), Some(account_user2()));
+        assert_eq!(contract.own_get_proposed_owner(), None);
+
+        contract.own_renounce_owner();
+        assert_eq!(contract.own_get_owner(), None);
+    }
+
     #[test]
     fn create_request() {
         let context = get_context(account_owner());
@@ -151,4 +174,102 @@ mod tests {
             VERIFICATION_FEE.into(),
         );
     }
+
+    /// A contract with one pending request, called by the owner with enough
+    /// deposit to resolve it.
+    fn contract_with_request() -> Contract {
+        let context = get_context(account_owner());
+        testing_env!(context.build());
+
+        let mut contract = Contract::new(account_owner(), VERIFICATION_FEE.into());
+
+        let mut context = get_context(account_user1());
+        context.attached_deposit(VERIFICATION_FEE + u128::pow(10, 22) /* storage fee */);
+        testing_env!(context.build());
+
+        contract.request_verification(
+            REPOSITORY_URL.to_string(),
+            CHECKOUT.to_string(),
+            PATH.to_string(),
+            VERIFICATION_FEE.into(),
+        );
+
+        let mut context = get_context(account_owner());
+        context.attached_deposit(u128::pow(10, 22) /* storage fee */);
+        testing_env!(context.build());
+
+        contract
+    }
+
+    #[test]
+    fn verification_failure_with_log() {
+        let mut contract = contract_with_request();
+
+        assert_eq!(
+            contract.get_verification_failure(0.into()),
+            None,
+            "No failure while pending"
+        );
+
+        let log = BuildLog {
+            hash: "ab".repeat(32),
+            url: Some(format!(
+                "https://registry.example.com/logs/{}",
+                "ab".repeat(32)
+            )),
+        };
+        contract.verification_failure(0.into(), Some(log.clone()));
+
+        assert_eq!(
+            contract.get_verification_request(0.into()).unwrap().status,
+            VerificationStatus::FAILURE,
+            "Status is failure"
+        );
+
+        assert_eq!(
+            contract.get_verification_failure(0.into()),
+            Some(VerificationFailure {
+                request_id: 0,
+                log: Some(log),
+            }),
+            "Failure records the build log"
+        );
+
+        assert_eq!(
+            contract.get_verification_result(0.into()),
+            None,
+            "No verification for failed request"
+        );
+    }
+
+    #[test]
+    fn verification_failure_without_log() {
+        let mut contract = contract_with_request();
+
+        contract.verification_failure(0.into(), None);
+
+        assert_eq!(
+            contract.get_verification_failure(0.into()),
+            Some(VerificationFailure {
+                request_id: 0,
+                log: None,
+            }),
+            "Failure recorded without build log"
+        );
+
+        assert_eq!(
+            contract.get_verification_failure(1.into()),
+            None,
+            "No failure for unknown request"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Request already resolved")]
+    fn verification_failure_twice() {
+        let mut contract = contract_with_request();
+
+        contract.verification_failure(0.into(), None);
+        contract.verification_failure(0.into(), None);
+    }
 }
@@ -0,0 +1,135 @@
+//! Upgrades state written by earlier versions of the contract.
+
+use model::{
+    code_hash::CodeHash,
+    verification::{Verification, VerificationRequest, VerificationStatus},
+};
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::{UnorderedMap, Vector},
+    env,
+    json_types::U128,
+    near_bindgen,
+};
+
+use crate::{ownership::Ownership, Contract, ContractContract, StorageKey};
+
+/// A request as stored before requesters were recorded.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct VerificationRequestV0 {
+    pub id: u64,
+    pub repository: String,
+    pub path: String,
+    pub checkout: String,
+    pub fee: U128,
+    pub status: VerificationStatus,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<VerificationRequestV0> for VerificationRequest {
+    fn from(request: VerificationRequestV0) -> Self {
+        Self {
+            id: request.id,
+            requester: None,
+            repository: request.repository,
+            path: request.path,
+            checkout: request.checkout,
+            fee: request.fee,
+            status: request.status,
+            created_at: request.created_at,
+            updated_at: request.updated_at,
+        }
+    }
+}
+
+/// Root state before requesters were recorded.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct ContractV0 {
+    pub ownership: Ownership,
+    pub requests: Vector<VerificationRequestV0>,
+    pub verifications: UnorderedMap<CodeHash, Verification>,
+    pub verification_fee: u128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Rewrites requests stored before requesters were recorded, whose
+    /// requester is unknown. Call once, in the same transaction as the
+    /// deployment of this version. Every request is rewritten, so very large
+    /// registries may need more than the maximum gas.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: ContractV0 =
+            env::state_read().unwrap_or_else(|| env::panic_str("No state to migrate"));
+
+        // Same prefix, so each push overwrites the request it was read from
+        let mut requests = Vector::new(StorageKey::REQUESTS);
+        for request in old.requests.iter() {
+            requests.push(&VerificationRequest::from(request));
+        }
+
+        Self {
+            ownership: old.ownership,
+            requests,
+            verifications: old.verifications,
+            verification_fee: old.verification_fee,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use model::verification::VerificationStatus;
+    use near_sdk::{
+        collections::{UnorderedMap, Vector},
+        env,
+        test_utils::VMContextBuilder,
+        testing_env,
+    };
+
+    use crate::{ownership::Ownership, Contract, StorageKey};
+
+    use super::{ContractV0, VerificationRequestV0};
+
+    #[test]
+    fn migrates_requests_without_requester() {
+        let contract_id = "contract".parse().unwrap();
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(contract_id)
+            .predecessor_account_id("contract".parse().unwrap())
+            .build());
+
+        let mut old = ContractV0 {
+            ownership: Ownership::new(StorageKey::OWNERSHIP, "owner".parse().unwrap()),
+            requests: Vector::new(StorageKey::REQUESTS),
+            verifications: UnorderedMap::new(StorageKey::VERIFICATIONS),
+            verification_fee: 1,
+        };
+        for id in 0..2 {
+            old.requests.push(&VerificationRequestV0 {
+                id,
+                repository: format!("https://github.com/example/contract-{id}.git"),
+                path: "".to_string(),
+                checkout: "main".to_string(),
+                fee: 1.into(),
+                status: VerificationStatus::PENDING,
+                created_at: 0,
+                updated_at: 0,
+            });
+        }
+        env::state_write(&old);
+
+        let contract = Contract::migrate();
+
+        let requests = contract.get_pending_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[1].repository,
+            "https://github.com/example/contract-1.git"
+        );
+        assert!(requests.iter().all(|r| r.requester.is_none()));
+        assert_eq!(u128::from(contract.get_verification_fee()), 1);
+    }
+}
@@ -1,7 +1,7 @@
 use near_sdk::{env, log, require, Balance, Promise, StorageUsage};
 
-pub(crate) fn prefix_key(prefix: &Vec<u8>, key: &[u8]) -> Vec<u8> {
-    [prefix as &[u8], key].concat()
+pub(crate) fn prefix_key(prefix: &[u8], key: &[u8]) -> Vec<u8> {
+    [prefix, key].concat()
 }
 
 pub(crate) fn storage_refund(storage_usage_start: StorageUsage, other_fees: Balance) {
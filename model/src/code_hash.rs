@@ -64,7 +64,7 @@ impl<'de> Visitor<'de> for CodeHashVisitor {
     {
         bs58::decode(v)
             .into_vec()
-            .map(|v| CodeHash(v))
+            .map(CodeHash)
             .map_err(|e| E::custom(format!("base58 decode error: {}", e)))
     }
 }
@@ -2,6 +2,7 @@ use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     json_types::U128,
     serde::{Deserialize, Serialize},
+    AccountId,
 };
 
 use crate::{code_hash::CodeHash, sequential_id::SequentialId};
@@ -27,10 +28,28 @@ pub struct Verification {
     pub request_id: u64,
 }
 
+/// Output of the build that resolved a request, published by the service.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BuildLog {
+    /// Hex-encoded SHA-256 of the log
+    pub hash: String,
+    pub url: Option<String>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VerificationFailure {
+    pub request_id: u64,
+    pub log: Option<BuildLog>,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, PartialEq, Debug, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct VerificationRequest {
     pub id: u64,
+    /// `None` for requests made before requesters were recorded
+    pub requester: Option<AccountId>,
     pub repository: String,
     pub path: String,
     pub checkout: String,
@@ -56,6 +75,7 @@ mod tests {
     fn test() {
         let s = serde_json::to_string(&VerificationRequest {
             id: 0,
+            requester: Some("alice.near".parse().unwrap()),
             repository: "repository".to_string(),
             checkout: "main".to_string(),
             path: "".to_string(),
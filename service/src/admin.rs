@@ -0,0 +1,144 @@
+//! Operator routes for intervening on requests, protected by bearer tokens.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+use warp::{reject, Filter, Rejection, Reply};
+
+use crate::pipeline::{ManualResolution, Pipeline};
+
+/// Largest accepted body of a manual resolution.
+const BODY_LIMIT: u64 = 16 * 1024;
+
+#[derive(Debug)]
+pub struct Unauthorized;
+impl reject::Reject for Unauthorized {}
+
+#[derive(Serialize)]
+struct OutcomeResponse {
+    outcome: String,
+}
+
+#[derive(Serialize)]
+struct RebuildResponse {
+    commit: String,
+}
+
+/// Whether `header` carries any of `tokens`. Digests are compared instead of
+/// the tokens themselves, so that timing does not reveal how much of a token
+/// was guessed right.
+pub(crate) fn authorized(tokens: &[String], header: Option<&str>) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    let given = Sha256::digest(given.trim().as_bytes());
+
+    tokens.iter().fold(false, |found, token| {
+        found | (Sha256::digest(token.as_bytes()) == given)
+    })
+}
+
+/// Rejects requests without one of `tokens` as bearer token.
+pub fn auth_filter(tokens: Vec<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let result = if authorized(&tokens, header.as_deref()) {
+                Ok(())
+            } else {
+                warn!("Rejected request without a valid bearer token");
+                Err(reject::custom(Unauthorized))
+            };
+            futures::future::ready(result)
+        })
+        .untuple_one()
+}
+
+async fn get_request(id: u64, pipeline: Pipeline) -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&pipeline.correlation(id).await?))
+}
+
+async fn rebuild(id: u64, pipeline: Pipeline) -> Result<impl Reply, Rejection> {
+    info!(request_id = id, "Rebuild requested by admin");
+    let commit = pipeline.rebuild(id).await?;
+
+    Ok(warp::reply::json(&RebuildResponse { commit }))
+}
+
+async fn resolve(
+    id: u64,
+    resolution: ManualResolution,
+    pipeline: Pipeline,
+) -> Result<impl Reply, Rejection> {
+    info!(
+        request_id = id,
+        ?resolution,
+        "Resolution requested by admin"
+    );
+    let outcome = pipeline.resolve_manually(id, resolution).await?;
+
+    Ok(warp::reply::json(&OutcomeResponse { outcome }))
+}
+
+async fn dead_letters(pipeline: Pipeline) -> Result<impl Reply, Rejection> {
+    Ok(warp::reply::json(&pipeline.builds().dead_letters()))
+}
+
+async fn replay(id: u64, pipeline: Pipeline) -> Result<impl Reply, Rejection> {
+    info!(request_id = id, "Replay requested by admin");
+    let outcome = pipeline.replay(id).await?;
+
+    Ok(warp::reply::json(&OutcomeResponse { outcome }))
+}
+
+/// Admin routes, under `/admin`. Every request is rejected if `tokens` is
+/// empty.
+pub fn routes(
+    pipeline: Pipeline,
+    tokens: Vec<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let pipeline = warp::any().map(move || pipeline.clone());
+
+    let request = warp::get()
+        .and(warp::path!("requests" / u64))
+        .and(pipeline.clone())
+        .and_then(get_request);
+    let rebuild = warp::post()
+        .and(warp::path!("requests" / u64 / "rebuild"))
+        .and(pipeline.clone())
+        .and_then(rebuild);
+    let resolve = warp::post()
+        .and(warp::path!("requests" / u64 / "resolve"))
+        .and(warp::body::content_length_limit(BODY_LIMIT))
+        .and(warp::body::json())
+        .and(pipeline.clone())
+        .and_then(resolve);
+    let dead_letters = warp::get()
+        .and(warp::path!("dead-letters"))
+        .and(pipeline.clone())
+        .and_then(dead_letters);
+    let replay = warp::post()
+        .and(warp::path!("dead-letters" / u64 / "replay"))
+        .and(pipeline)
+        .and_then(replay);
+
+    warp::path("admin")
+        .and(auth_filter(tokens))
+        .and(request.or(rebuild).or(resolve).or(dead_letters).or(replay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::authorized;
+
+    #[test]
+    fn checks_bearer_token() {
+        let tokens = vec!["old".to_string(), "new".to_string()];
+
+        assert!(authorized(&tokens, Some("Bearer new")));
+        assert!(authorized(&tokens, Some("Bearer old")));
+        assert!(!authorized(&tokens, Some("Bearer ne")));
+        assert!(!authorized(&tokens, Some("new")));
+        assert!(!authorized(&tokens, None));
+        assert!(!authorized(&[], Some("Bearer ")));
+    }
+}
@@ -0,0 +1,111 @@
+//! Registration of credentials for private repositories by requesters, and
+//! retrieval by CI (`GET /ci/requests/{id}/credential`, with the CI token of
+//! the request).
+//! Builds that ran before their credential was registered are started over.
+
+use serde::Serialize;
+use tracing::{info, warn};
+use warp::{http::StatusCode, reject, Filter, Rejection, Reply};
+
+use crate::{
+    admin::{authorized, Unauthorized},
+    pipeline::Pipeline,
+    repository::access::{AccessError, CredentialStore, Registration},
+};
+
+/// Deploy keys are a few kilobytes at most.
+const BODY_LIMIT: u64 = 16 * 1024;
+
+#[derive(Serialize)]
+struct RegisteredResponse {
+    request_id: u64,
+    /// Commit of the build that was started over with the credential
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rebuild_commit: Option<String>,
+}
+
+fn enabled(pipeline: &Pipeline) -> Result<&CredentialStore, Rejection> {
+    pipeline
+        .credentials()
+        .ok_or_else(|| reject::custom(AccessError::Disabled))
+}
+
+async fn register(
+    id: u64,
+    registration: Registration,
+    pipeline: Pipeline,
+) -> Result<impl Reply, Rejection> {
+    let store = enabled(&pipeline)?;
+    let request = registration.verify(pipeline.registry(), id).await?;
+
+    store.insert(id, &registration);
+    info!(
+        request_id = id,
+        requester = ?request.requester,
+        public_key = %registration.public_key,
+        "Registered repository credential"
+    );
+
+    // The credential is stored either way, and the build can be restarted
+    // with `rebuild`
+    let rebuild_commit = pipeline
+        .credential_registered(id)
+        .await
+        .unwrap_or_else(|e| {
+            warn!(
+                request_id = id,
+                "Could not rebuild with the credential: {e}"
+            );
+            None
+        });
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&RegisteredResponse {
+            request_id: id,
+            rebuild_commit,
+        }),
+        StatusCode::CREATED,
+    ))
+}
+
+async fn get_credential(
+    id: u64,
+    authorization: Option<String>,
+    pipeline: Pipeline,
+) -> Result<impl Reply, Rejection> {
+    let store = enabled(&pipeline)?;
+    if !authorized(&[store.ci_token(id)], authorization.as_deref()) {
+        warn!(
+            request_id = id,
+            "Rejected credential fetch without the CI token of the request"
+        );
+        return Err(reject::custom(Unauthorized));
+    }
+
+    let credential = store.fetch(id)?;
+    info!(request_id = id, "Repository credential fetched by CI");
+
+    Ok(warp::reply::json(&credential))
+}
+
+/// `POST /requests/{id}/credential`, and the CI route. Both reject every
+/// request unless private repositories are enabled.
+pub fn routes(
+    pipeline: Pipeline,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let pipeline = warp::any().map(move || pipeline.clone());
+
+    let register = warp::post()
+        .and(warp::path!("requests" / u64 / "credential"))
+        .and(warp::body::content_length_limit(BODY_LIMIT))
+        .and(warp::body::json())
+        .and(pipeline.clone())
+        .and_then(register);
+    let get = warp::get()
+        .and(warp::path!("ci" / "requests" / u64 / "credential"))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(pipeline)
+        .and_then(get_credential);
+
+    register.or(get)
+}
@@ -0,0 +1,244 @@
+//! SVG badges for READMEs, e.g.
+//! `![Verified](https://registry.example.com/badge/example.near.svg)`.
+//!
+//! The registry only records successful verifications, so a badge can only
+//! show "pending" if it names the request to check with `?request=<id>`.
+//! Requests only record their requester, not the code they build, so only
+//! account badges can show "pending", and only for requests made by that
+//! account. Other requests are ignored.
+
+use model::verification::VerificationStatus;
+use near_primitives::types::AccountId;
+use serde::Deserialize;
+use serde_json::Value;
+use warp::{
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    Filter, Rejection, Reply,
+};
+
+use super::{parse_account_id, parse_code_hash, ApiError, ApiState};
+
+const LABEL: &str = "contract registry";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BadgeStatus {
+    Verified,
+    Pending,
+    Unverified,
+    /// The registry could not be queried
+    Unknown,
+}
+
+impl BadgeStatus {
+    fn message(self) -> &'static str {
+        match self {
+            Self::Verified => "verified",
+            Self::Pending => "pending",
+            Self::Unverified => "unverified",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    fn color(self) -> &'static str {
+        match self {
+            Self::Verified => "#4c1",
+            Self::Pending => "#dfb317",
+            Self::Unverified => "#e05d44",
+            Self::Unknown => "#9f9f9f",
+        }
+    }
+}
+
+/// What a badge reports on.
+#[derive(Debug, Clone, Copy)]
+enum BadgeSubject<'a> {
+    Account(&'a AccountId),
+    Code,
+}
+
+impl BadgeSubject<'_> {
+    /// Whether the request named in the query concerns this badge, so its
+    /// status may be shown. `request` is a `RequestResponse`.
+    fn matches(self, request: &Value) -> bool {
+        match self {
+            Self::Account(account_id) => request["requester"] == account_id.as_str(),
+            // The code hash of a request is only known once it is verified
+            Self::Code => false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct BadgeQuery {
+    /// Request to report as pending while the code is not yet verified
+    request: Option<u64>,
+}
+
+/// Approximate rendered width of `text` in 11px Verdana.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn render(status: BadgeStatus) -> String {
+    let message = status.message();
+    let label_width = text_width(LABEL);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {message}"><title>{LABEL}: {message}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="14">{LABEL}</text><text x="{message_x}" y="14">{message}</text></g></svg>"##,
+        color = status.color(),
+        label_x = label_width / 2,
+        message_x = label_width + message_width / 2,
+    )
+}
+
+fn reply(status: BadgeStatus, max_age: u64) -> impl Reply {
+    // Don't let caches hold on to a transient failure
+    let cache_control = match status {
+        BadgeStatus::Unknown => "no-cache".to_string(),
+        _ => format!("public, max-age={max_age}"),
+    };
+
+    let reply = warp::reply::with_header(render(status), CONTENT_TYPE, "image/svg+xml");
+    warp::reply::with_header(reply, CACHE_CONTROL, cache_control)
+}
+
+/// Status of unverified code given the request named in the query.
+fn request_status(subject: BadgeSubject, request: &Value) -> BadgeStatus {
+    let pending = serde_json::to_value(VerificationStatus::PENDING).unwrap();
+    if subject.matches(request) && request["status"] == pending {
+        BadgeStatus::Pending
+    } else {
+        BadgeStatus::Unverified
+    }
+}
+
+/// Resolves the status of unverified code from the request named in the
+/// query, if any and if it concerns the badge.
+async fn unverified_status(
+    state: &ApiState,
+    subject: BadgeSubject<'_>,
+    query: &BadgeQuery,
+) -> Result<BadgeStatus, ApiError> {
+    match (subject, query.request) {
+        // Don't look up requests that can't match
+        (BadgeSubject::Code, _) | (_, None) => Ok(BadgeStatus::Unverified),
+        (BadgeSubject::Account(_), Some(id)) => {
+            Ok(request_status(subject, &state.request(id).await?))
+        }
+    }
+}
+
+async fn verification_status(
+    state: &ApiState,
+    subject: BadgeSubject<'_>,
+    verification: &Value,
+    query: &BadgeQuery,
+) -> Result<BadgeStatus, ApiError> {
+    if verification.is_null() {
+        unverified_status(state, subject, query).await
+    } else {
+        Ok(BadgeStatus::Verified)
+    }
+}
+
+fn strip_extension(file_name: &str) -> Result<&str, ApiError> {
+    file_name
+        .strip_suffix(".svg")
+        .ok_or_else(|| ApiError::NotFound("Badges are only available as .svg".to_string()))
+}
+
+async fn account_badge(
+    file_name: String,
+    query: BadgeQuery,
+    state: ApiState,
+) -> Result<impl Reply, Rejection> {
+    let account_id = parse_account_id(strip_extension(&file_name)?)?;
+
+    let status = match state.account_verification(&account_id).await {
+        // No contract deployed
+        Ok(Value::Null) => Ok(BadgeStatus::Unverified),
+        Ok(response) => {
+            let subject = BadgeSubject::Account(&account_id);
+            verification_status(&state, subject, &response["verification"], &query).await
+        }
+        Err(e) => Err(e),
+    }
+    .unwrap_or(BadgeStatus::Unknown);
+
+    Ok(reply(status, state.cache.ttl().as_secs()))
+}
+
+async fn code_badge(
+    file_name: String,
+    query: BadgeQuery,
+    state: ApiState,
+) -> Result<impl Reply, Rejection> {
+    let code_hash = parse_code_hash(strip_extension(&file_name)?)?;
+
+    let status = match state.verification(&code_hash).await {
+        Ok(verification) => {
+            let subject = BadgeSubject::Code;
+            verification_status(&state, subject, &verification, &query).await
+        }
+        Err(e) => Err(e),
+    }
+    .unwrap_or(BadgeStatus::Unknown);
+
+    Ok(reply(status, state.cache.ttl().as_secs()))
+}
+
+pub(super) fn routes(
+    state: ApiState,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let state = warp::any().map(move || state.clone());
+
+    let account = warp::path!("badge" / String)
+        .and(warp::query::<BadgeQuery>())
+        .and(state.clone())
+        .and_then(account_badge);
+    let code = warp::path!("badge" / "code" / String)
+        .and(warp::query::<BadgeQuery>())
+        .and(state)
+        .and_then(code_badge);
+
+    code.or(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{render, request_status, BadgeStatus, BadgeSubject};
+
+    #[test]
+    fn renders_status() {
+        let svg = render(BadgeStatus::Verified);
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(">verified</text>"));
+        assert!(svg.contains("#4c1"));
+    }
+
+    #[test]
+    fn only_matching_requests_are_pending() {
+        let account_id = "example.near".parse().unwrap();
+        let request =
+            |requester: &str| json!({ "id": "1", "requester": requester, "status": "PENDING" });
+
+        let account = BadgeSubject::Account(&account_id);
+        assert_eq!(
+            request_status(account, &request("example.near")),
+            BadgeStatus::Pending
+        );
+        assert_eq!(
+            request_status(account, &request("other.near")),
+            BadgeStatus::Unverified
+        );
+        assert_eq!(
+            request_status(BadgeSubject::Code, &request("example.near")),
+            BadgeStatus::Unverified
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+/// Least recently used entries are evicted beyond this many.
+pub const MAX_ENTRIES: usize = 10_000;
+
+struct Entry {
+    inserted_at: Instant,
+    /// Position in [`Entries::recency`]
+    used: u64,
+    value: Value,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_key: HashMap<String, Entry>,
+    /// Keys by when they were last used, least recently used first
+    recency: BTreeMap<u64, String>,
+    next_use: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) {
+        let Some(entry) = self.by_key.get_mut(key) else {
+            return;
+        };
+        self.recency.remove(&entry.used);
+        entry.used = self.next_use;
+        self.recency.insert(self.next_use, key.to_string());
+        self.next_use += 1;
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.by_key.remove(key) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+/// Responses of recent contract views, so that popular lookups don't each
+/// cost an RPC round trip.
+pub struct Cache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_max_entries(ttl, MAX_ENTRIES)
+    }
+
+    pub fn with_max_entries(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.by_key.get(key)?;
+        if entry.inserted_at.elapsed() >= self.ttl {
+            entries.remove(key);
+            return None;
+        }
+
+        let value = entry.value.clone();
+        entries.touch(key);
+        Some(value)
+    }
+
+    pub fn insert(&self, key: String, value: Value) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&key);
+
+        while entries.by_key.len() >= self.max_entries {
+            let Some((_, oldest)) = entries.recency.pop_first() else {
+                break;
+            };
+            entries.by_key.remove(&oldest);
+        }
+
+        let used = entries.next_use;
+        entries.next_use += 1;
+        entries.recency.insert(used, key.clone());
+        entries.by_key.insert(
+            key,
+            Entry {
+                inserted_at: Instant::now(),
+                used,
+                value,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::Cache;
+
+    #[test]
+    fn entries_expire() {
+        let cache = Cache::new(Duration::from_millis(50));
+        cache.insert("a".to_string(), json!(1));
+        assert_eq!(cache.get("a"), Some(json!(1)));
+        assert_eq!(cache.get("b"), None);
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(cache.get("a"), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = Cache::with_max_entries(Duration::from_secs(60), 2);
+        cache.insert("a".to_string(), json!(1));
+        cache.insert("b".to_string(), json!(2));
+        assert_eq!(cache.get("a"), Some(json!(1)));
+
+        cache.insert("c".to_string(), json!(3));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(json!(1)));
+        assert_eq!(cache.get("c"), Some(json!(3)));
+
+        // Replacing an entry does not evict another
+        cache.insert("c".to_string(), json!(4));
+        assert_eq!(cache.get("a"), Some(json!(1)));
+        assert_eq!(cache.get("c"), Some(json!(4)));
+    }
+}
@@ -0,0 +1,32 @@
+//! Build logs by hash, e.g. as linked from the on-chain failure record of a
+//! request.
+
+use warp::{
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    Filter, Rejection, Reply,
+};
+
+use crate::pipeline::logs::LogStore;
+
+use super::ApiError;
+
+async fn get_log(hash: String, store: LogStore) -> Result<impl Reply, Rejection> {
+    let log = store
+        .get(&hash)
+        .ok_or_else(|| ApiError::NotFound(format!("Build log {hash} does not exist")))?;
+
+    let reply = warp::reply::with_header(log, CONTENT_TYPE, "text/plain; charset=utf-8");
+    // The content of a hash never changes
+    Ok(warp::reply::with_header(
+        reply,
+        CACHE_CONTROL,
+        "public, max-age=31536000, immutable",
+    ))
+}
+
+/// `GET /logs/{hash}`
+pub fn routes(store: LogStore) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("logs" / String)
+        .and(warp::any().map(move || store.clone()))
+        .and_then(get_log)
+}
@@ -0,0 +1,313 @@
+//! Public, read-only JSON API over the registry contract's views.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use model::{
+    code_hash::CodeHash,
+    verification::{Verification, VerificationFailure, VerificationRequest},
+};
+use near_primitives::types::AccountId;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use warp::{reject::Reject, Filter, Rejection, Reply};
+
+use crate::{
+    contract_interaction::{registry::RegistryClient, ContractInteractionError},
+    events::Events,
+    pipeline::logs::LogStore,
+};
+
+use self::cache::Cache;
+
+pub mod access;
+pub mod badge;
+pub mod cache;
+pub mod logs;
+pub mod stream;
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    InvalidParameter(String),
+    #[error("Could not query the registry: {0}")]
+    Upstream(String),
+}
+
+impl Reject for ApiError {}
+
+impl From<ContractInteractionError> for ApiError {
+    fn from(e: ContractInteractionError) -> Self {
+        Self::Upstream(e.to_string())
+    }
+}
+
+#[derive(Clone)]
+struct ApiState {
+    registry: RegistryClient,
+    cache: Arc<Cache>,
+}
+
+impl ApiState {
+    /// Returns the cached response for `key`, or computes and caches it.
+    async fn cached<T, F>(&self, key: String, compute: F) -> Result<Value, ApiError>
+    where
+        T: Serialize,
+        F: Future<Output = Result<T, ContractInteractionError>>,
+    {
+        if let Some(value) = self.cache.get(&key) {
+            return Ok(value);
+        }
+
+        let value =
+            serde_json::to_value(compute.await?).map_err(|e| ApiError::Upstream(e.to_string()))?;
+        self.cache.insert(key, value.clone());
+
+        Ok(value)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RequestStatusFilter {
+    Pending,
+}
+
+#[derive(Deserialize)]
+struct RequestsQuery {
+    status: RequestStatusFilter,
+}
+
+#[derive(Serialize)]
+struct RequestResponse {
+    #[serde(flatten)]
+    request: VerificationRequest,
+    verification: Option<Verification>,
+    /// Includes the build log, if the request failed
+    failure: Option<VerificationFailure>,
+}
+
+#[derive(Serialize)]
+struct AccountVerificationResponse {
+    account_id: AccountId,
+    code_hash: CodeHash,
+    verification: Option<Verification>,
+}
+
+fn parse_code_hash(code_hash: &str) -> Result<CodeHash, ApiError> {
+    serde_json::from_value(Value::String(code_hash.to_string()))
+        .map_err(|e| ApiError::InvalidParameter(format!("Invalid code hash: {e}")))
+}
+
+fn parse_account_id(account_id: &str) -> Result<AccountId, ApiError> {
+    account_id
+        .parse()
+        .map_err(|e| ApiError::InvalidParameter(format!("Invalid account ID: {e}")))
+}
+
+fn not_found(value: Value, message: impl FnOnce() -> String) -> Result<Value, ApiError> {
+    if value.is_null() {
+        Err(ApiError::NotFound(message()))
+    } else {
+        Ok(value)
+    }
+}
+
+impl ApiState {
+    /// `Option<Verification>`
+    async fn verification(&self, code_hash: &CodeHash) -> Result<Value, ApiError> {
+        self.cached(
+            format!("verifications/{code_hash}"),
+            self.registry.verify_code_hash(code_hash),
+        )
+        .await
+    }
+
+    /// `Option<AccountVerificationResponse>`
+    async fn account_verification(&self, account_id: &AccountId) -> Result<Value, ApiError> {
+        self.cached(format!("accounts/{account_id}"), async {
+            let response =
+                self.registry
+                    .verify_account(account_id)
+                    .await?
+                    .map(|(code_hash, verification)| AccountVerificationResponse {
+                        account_id: account_id.clone(),
+                        code_hash,
+                        verification,
+                    });
+            Ok(response)
+        })
+        .await
+    }
+
+    /// `Option<RequestResponse>`
+    async fn request(&self, id: u64) -> Result<Value, ApiError> {
+        self.cached(format!("requests/{id}"), async {
+            let request = match self.registry.get_verification_request(id).await? {
+                Some(request) => request,
+                None => return Ok(None),
+            };
+            let (verification, failure) = futures::try_join!(
+                self.registry.get_verification_result(id),
+                self.registry.get_verification_failure(id),
+            )?;
+            Ok(Some(RequestResponse {
+                request,
+                verification,
+                failure,
+            }))
+        })
+        .await
+    }
+}
+
+async fn get_verification(code_hash: String, state: ApiState) -> Result<impl Reply, Rejection> {
+    let code_hash = parse_code_hash(&code_hash)?;
+    let value = state.verification(&code_hash).await?;
+    let value = not_found(value, || format!("Code hash {code_hash} is not verified"))?;
+
+    Ok(warp::reply::json(&value))
+}
+
+async fn get_account_verification(
+    account_id: String,
+    state: ApiState,
+) -> Result<impl Reply, Rejection> {
+    let account_id = parse_account_id(&account_id)?;
+    let value = state.account_verification(&account_id).await?;
+    let value = not_found(value, || format!("No contract is deployed to {account_id}"))?;
+
+    Ok(warp::reply::json(&value))
+}
+
+async fn get_request(id: u64, state: ApiState) -> Result<impl Reply, Rejection> {
+    let value = state.request(id).await?;
+    let value = not_found(value, || format!("Request {id} does not exist"))?;
+
+    Ok(warp::reply::json(&value))
+}
+
+async fn get_requests(query: RequestsQuery, state: ApiState) -> Result<impl Reply, Rejection> {
+    let value = match query.status {
+        RequestStatusFilter::Pending => {
+            state
+                .cached(
+                    "requests?status=pending".to_string(),
+                    state.registry.get_pending_requests(),
+                )
+                .await?
+        }
+    };
+
+    Ok(warp::reply::json(&value))
+}
+
+/// All API routes. Responses are cached for `cache_ttl`.
+pub fn routes(
+    registry: RegistryClient,
+    events: Events,
+    logs: LogStore,
+    cache_ttl: Duration,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let state = ApiState {
+        registry,
+        cache: Arc::new(Cache::new(cache_ttl)),
+    };
+    let badges = badge::routes(state.clone());
+    let state = warp::any().map(move || state.clone());
+
+    let verification = warp::path!("verifications" / String)
+        .and(state.clone())
+        .and_then(get_verification);
+    let account_verification = warp::path!("accounts" / String / "verification")
+        .and(state.clone())
+        .and_then(get_account_verification);
+    let request = warp::path!("requests" / u64)
+        .and(state.clone())
+        .and_then(get_request);
+    let requests = warp::path!("requests")
+        .and(warp::query::<RequestsQuery>())
+        .and(state)
+        .and_then(get_requests);
+
+    let cors = warp::cors().allow_any_origin().allow_method("GET");
+
+    warp::get()
+        .and(
+            verification
+                .or(account_verification)
+                .or(request)
+                .or(requests)
+                .or(badges)
+                .or(logs::routes(logs))
+                .or(stream::routes(events)),
+        )
+        .with(cors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use warp::http::{header::CACHE_CONTROL, StatusCode};
+    use warp::Filter;
+
+    use crate::{
+        contract_interaction::registry::RegistryClient, events::Events, network_config,
+        pipeline::logs::LogStore, rejection::recover,
+    };
+
+    use super::routes;
+
+    fn api(
+    ) -> impl warp::Filter<Extract = (impl warp::Reply,), Error = std::convert::Infallible> + Clone
+    {
+        let mut network = network_config::preset("localnet").unwrap();
+        // Nothing listens here, so every registry lookup fails
+        network.node_url = "http://127.0.0.1:1".to_string();
+        let registry = RegistryClient::new(network, "registry.test.near".parse().unwrap());
+
+        let logs = LogStore::new(&std::env::temp_dir().join("api-logs"), None);
+
+        routes(registry, Events::default(), logs, Duration::from_secs(10)).recover(recover)
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_parameters() {
+        let api = api();
+
+        for path in [
+            "/requests?status=bogus",
+            "/verifications/not-base58!",
+            "/accounts/Not%20An%20Account/verification",
+        ] {
+            let res = warp::test::request().path(path).reply(&api).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_log_is_not_found() {
+        for path in [
+            "/logs/..%2Fstate.json",
+            &format!("/logs/{}", "0".repeat(64)),
+        ] {
+            let res = warp::test::request().path(path).reply(&api()).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn badge_reports_unknown_status() {
+        let res = warp::test::request()
+            .path("/badge/example.near.svg")
+            .reply(&api())
+            .await;
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[CACHE_CONTROL], "no-cache");
+        assert!(String::from_utf8_lossy(res.body()).contains(">unknown</text>"));
+    }
+}
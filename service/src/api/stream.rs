@@ -0,0 +1,43 @@
+//! Server-sent events of request progress, e.g. `GET /events?request_id=12`
+//! or `GET /events?requester=alice.near`.
+
+use std::convert::Infallible;
+
+use futures::{future, stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::warn;
+use warp::{sse, Filter, Rejection, Reply};
+
+use crate::events::{EventFilter, Events};
+
+async fn subscribe(filter: EventFilter, events: Events) -> Result<impl Reply, Infallible> {
+    let closed = Box::pin({
+        let events = events.clone();
+        async move { events.closed().await }
+    });
+    let events = stream::unfold(events.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Event subscriber missed {missed} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter(move |event| future::ready(filter.matches(event)))
+    .map(|event| sse::Event::default().event(event.name()).json_data(&event))
+    .take_until(closed);
+
+    Ok(sse::reply(sse::keep_alive().stream(events)))
+}
+
+pub(super) fn routes(
+    events: Events,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::path!("events")
+        .and(warp::query::<EventFilter>())
+        .and(warp::any().map(move || events.clone()))
+        .and_then(subscribe)
+}
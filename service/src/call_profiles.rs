@@ -0,0 +1,97 @@
+use std::{collections::HashMap, fs};
+
+use near_primitives::{
+    serialize::u128_dec_format,
+    types::{Balance, Gas},
+};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_GAS: Gas = 100_000_000_000_000; // 100 TGas
+
+/// Gas and deposit attached to calls of a single contract method.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CallProfile {
+    pub gas: Gas,
+    /// For `request_verification`, this is attached in addition to the fee.
+    #[serde(with = "u128_dec_format")]
+    pub deposit: Balance,
+}
+
+/// Per-method overrides for gas and deposit, e.g.:
+///
+/// ```json
+/// {
+///     "verification_success": { "gas": 150000000000000, "deposit": "20000000000000000000000" }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CallProfiles(HashMap<String, CallProfile>);
+
+impl CallProfiles {
+    pub fn get(&self, method: &str) -> CallProfile {
+        self.0
+            .get(method)
+            .copied()
+            .unwrap_or_else(|| default_profile(method))
+    }
+}
+
+impl From<HashMap<String, CallProfile>> for CallProfiles {
+    fn from(profiles: HashMap<String, CallProfile>) -> Self {
+        Self(profiles)
+    }
+}
+
+fn default_profile(method: &str) -> CallProfile {
+    let deposit = match method {
+        // Covers storage of the new verification or failure record; excess
+        // is refunded
+        "verification_success" | "verification_failure" => 10u128.pow(22), // 0.01 NEAR
+        // Covers storage of the request; excess is refunded
+        "request_verification" => 10u128.pow(22), // 0.01 NEAR
+        _ => 0,
+    };
+
+    CallProfile {
+        gas: DEFAULT_GAS,
+        deposit,
+    }
+}
+
+pub fn load(path: &str) -> Result<CallProfiles, String> {
+    let handle = fs::File::open(path)
+        .map_err(|e| format!("Could not load call profiles path {path}: {e}"))?;
+    let reader = std::io::BufReader::new(handle);
+
+    serde_json::from_reader(reader)
+        .map_err(|e| format!("Could not parse call profiles file {path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallProfile, CallProfiles, DEFAULT_GAS};
+
+    #[test]
+    fn overrides_and_defaults() {
+        let profiles: CallProfiles = serde_json::from_str(
+            r#"{ "verification_success": { "gas": 150000000000000, "deposit": "20000000000000000000000" } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profiles.get("verification_success"),
+            CallProfile {
+                gas: 150_000_000_000_000,
+                deposit: 2 * 10u128.pow(22),
+            },
+        );
+        assert_eq!(
+            profiles.get("verification_failure"),
+            CallProfile {
+                gas: DEFAULT_GAS,
+                deposit: 10u128.pow(22),
+            },
+        );
+    }
+}
@@ -1,40 +1,65 @@
 /// https://circleci.com/docs/2.0/webhooks/#headers
-use hex;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use thiserror::Error;
+use tracing::{debug, warn};
 use warp::{reject, Filter};
 
-const SIGNATURE_VERSION: &'static str = "v1";
-const SIGNATURE_HEADER: &'static str = "circleci-signature";
+const SIGNATURE_VERSION: &str = "v1";
+const SIGNATURE_HEADER: &str = "circleci-signature";
 
 pub fn extract_compatible_signature(header: &str) -> Option<&str> {
     header
-        .split(",")
-        .find_map(|pair| match pair.split("=").collect::<Vec<&str>>()[..] {
+        .split(',')
+        .find_map(|pair| match pair.split('=').collect::<Vec<&str>>()[..] {
             [v, sig] if v == SIGNATURE_VERSION => Some(sig),
             _ => None,
         })
 }
 
-pub fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
-    println!("Verifying signature");
+/// Parses a comma-separated list of webhook secrets. Multiple secrets may be
+/// active at once so that a secret can be rotated without downtime.
+pub fn parse_secrets(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns `Ok(true)` if `signature` is valid for `body` under any of the
+/// provided secrets.
+pub fn verify_signature(
+    secrets: &[String],
+    signature: &str,
+    body: &[u8],
+) -> Result<bool, MalformedSignature> {
+    let signature = hex::decode(signature)?;
 
-    let mut h = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
-    h.update(body);
-    h.verify(hex::decode(signature).unwrap().as_slice().into())
-        .is_ok()
+    Ok(secrets.iter().any(|secret| {
+        let mut h = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC can take key of any size");
+        h.update(body);
+        h.verify_slice(&signature).is_ok()
+    }))
 }
 
 #[derive(Debug)]
-struct InvalidSignature;
+pub struct InvalidSignature;
 impl reject::Reject for InvalidSignature {}
 
 #[derive(Debug)]
-struct IncompatibleSignatureVersion;
+pub struct IncompatibleSignatureVersion;
 impl reject::Reject for IncompatibleSignatureVersion {}
 
+#[derive(Error, Debug)]
+#[error("Signature is not valid hex: {0}")]
+pub struct MalformedSignature(#[from] hex::FromHexError);
+impl reject::Reject for MalformedSignature {}
+
 pub fn verify_filter(
-    secret: String,
+    secrets: Vec<String>,
 ) -> impl Filter<Extract = (warp::hyper::body::Bytes,), Error = warp::Rejection> + Clone {
     warp::header::<String>(SIGNATURE_HEADER)
         .and(warp::body::bytes())
@@ -42,15 +67,62 @@ pub fn verify_filter(
             let signature = extract_compatible_signature(&header);
             match signature {
                 None => futures::future::err(reject::custom(IncompatibleSignatureVersion)),
-                Some(signature) => {
-                    if verify_signature(&secret, signature, &body) {
-                        println!("Valid signature");
+                Some(signature) => match verify_signature(&secrets, signature, &body) {
+                    Ok(true) => {
+                        debug!("Valid signature");
                         futures::future::ok(body)
-                    } else {
-                        println!("Invalid signature");
+                    }
+                    Ok(false) => {
+                        warn!("Invalid signature");
                         futures::future::err(reject::custom(InvalidSignature))
                     }
-                }
+                    Err(e) => {
+                        warn!("Malformed signature: {e}");
+                        futures::future::err(reject::custom(e))
+                    }
+                },
             }
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::{extract_compatible_signature, parse_secrets, verify_signature};
+
+    const BODY: &[u8] = b"{\"job\":{\"name\":\"build\",\"status\":\"success\",\"number\":1}}";
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut h = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        h.update(body);
+        hex::encode(h.finalize().into_bytes())
+    }
+
+    #[test]
+    fn extract_signature() {
+        assert_eq!(extract_compatible_signature("v0=abc,v1=def"), Some("def"),);
+        assert_eq!(extract_compatible_signature("v0=abc"), None);
+    }
+
+    #[test]
+    fn rotated_secrets() {
+        let secrets = parse_secrets("old-secret, new-secret");
+        assert_eq!(secrets, vec!["old-secret", "new-secret"]);
+
+        let old = sign("old-secret", BODY);
+        let new = sign("new-secret", BODY);
+        let other = sign("other-secret", BODY);
+
+        assert!(verify_signature(&secrets, &old, BODY).unwrap());
+        assert!(verify_signature(&secrets, &new, BODY).unwrap());
+        assert!(!verify_signature(&secrets, &other, BODY).unwrap());
+    }
+
+    #[test]
+    fn malformed_signature() {
+        let secrets = parse_secrets("secret");
+        assert!(verify_signature(&secrets, "not hex", BODY).is_err());
+    }
+}
@@ -1,9 +1,9 @@
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tracing::{info, Span};
 use warp::{reject::Reject, Rejection};
 
-use super::client::request_job;
+use crate::pipeline::Pipeline;
 
 #[derive(Serialize, Deserialize)]
 pub struct WebhookPayloadJob {
@@ -12,9 +12,22 @@ pub struct WebhookPayloadJob {
     pub number: u64,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPayloadVcs {
+    /// Commit that the pipeline built
+    pub revision: String,
+    pub branch: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WebhookPayloadPipeline {
+    pub vcs: WebhookPayloadVcs,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct JobCompletedWebhookPayload {
     pub job: WebhookPayloadJob,
+    pub pipeline: WebhookPayloadPipeline,
 }
 
 #[derive(Debug, Error)]
@@ -25,19 +38,58 @@ pub enum WebhookError {
 
 impl Reject for WebhookError {}
 
+#[tracing::instrument(skip_all, fields(job_number, job_name, job_status))]
 pub async fn handler(
-    client: Client,
-    project_slug: String,
+    pipeline: Pipeline,
     body: warp::hyper::body::Bytes,
 ) -> Result<String, Rejection> {
-    let payload = serde_json::from_slice::<JobCompletedWebhookPayload>(&body)
-        .map_err(|e| WebhookError::from(e))?;
-    let job_number = payload.job.number.to_string();
-    println!("Webhook triggered for job {job_number}");
-    let meta = request_job(&client, project_slug, job_number).await?;
-    println!(
-        "Webhook results:\n\tbranch: {}\n\tcommit: {}\n\tcode hash: {}\n\tcode url: {}",
-        meta.branch, meta.commit, meta.code_hash, meta.code_url
-    );
-    Ok(format!("{}", meta.code_hash))
+    let payload =
+        serde_json::from_slice::<JobCompletedWebhookPayload>(&body).map_err(WebhookError::from)?;
+
+    let span = Span::current();
+    span.record("job_number", payload.job.number);
+    span.record("job_name", payload.job.name.as_str());
+    span.record("job_status", payload.job.status.as_str());
+    info!("Webhook received");
+
+    let outcome = pipeline.job_completed(payload).await?;
+    info!(%outcome, "Webhook handled");
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JobCompletedWebhookPayload;
+
+    #[test]
+    fn parse_job_completed_payload() {
+        // Abbreviated from https://circleci.com/docs/webhooks/#sample-webhook-payloads
+        let payload: JobCompletedWebhookPayload = serde_json::from_str(
+            r#"{
+                "type": "job-completed",
+                "pipeline": {
+                    "id": "1285fe1d-d3a6-44fc-8886-8979558254c4",
+                    "number": 130,
+                    "vcs": {
+                        "provider_name": "github",
+                        "revision": "1dc6aa69429bff4806ad6afe58d3d8f57e25973e",
+                        "branch": "main"
+                    }
+                },
+                "job": {
+                    "id": "8bd26027-1f3c-4d82-9c2e-9ec2ec49d27a",
+                    "name": "build",
+                    "number": 136,
+                    "status": "success"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            payload.pipeline.vcs.revision,
+            "1dc6aa69429bff4806ad6afe58d3d8f57e25973e"
+        );
+        assert_eq!(payload.job.number, 136);
+    }
 }
@@ -0,0 +1,324 @@
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand};
+use model::code_hash::CodeHash;
+use near_primitives::types::AccountId;
+use thiserror::Error;
+
+use crate::{
+    circleci::{client::ParallelError, error::CircleCiError},
+    config::{Config, RequiredSections},
+    contract_interaction::ContractInteractionError,
+    env,
+    network_config::NetworkCheckError,
+    pipeline::{builds::StateError, PipelineError},
+    signer::keystore::KeystoreError,
+    telemetry,
+};
+
+mod registry;
+mod serve;
+mod tools;
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error(transparent)]
+    ContractInteraction(Box<ContractInteractionError>),
+    #[error(transparent)]
+    Network(#[from] NetworkCheckError),
+    #[error("CircleCI error: {0}")]
+    CircleCi(#[from] ParallelError<CircleCiError>),
+    #[error("Could not read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Code hash {0} is not verified")]
+    Unverified(CodeHash),
+    #[error(transparent)]
+    State(#[from] StateError),
+    #[error(transparent)]
+    Pipeline(#[from] PipelineError),
+    #[error("Keystore error: {0}")]
+    Keystore(#[from] KeystoreError),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<ContractInteractionError> for CliError {
+    fn from(e: ContractInteractionError) -> Self {
+        Self::ContractInteraction(Box::new(e))
+    }
+}
+
+#[derive(Parser)]
+#[command(version, about = "Contract registry verification service")]
+pub struct Cli {
+    /// Path to the TOML configuration file [default: config.toml, if present]
+    #[arg(long, global = true, env = env::CONFIG)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the webhook server (default)
+    Serve,
+    /// Watch for pending verification requests and trigger a build for each
+    Watch {
+        /// Only print new requests, without updating the CI repository
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Resolve a pending verification request, regardless of its builds
+    Resolve {
+        #[command(subcommand)]
+        resolution: Resolution,
+    },
+    /// Submit a verification request, paying the current verification fee
+    Request {
+        /// URL of the repository to verify
+        repository: String,
+        /// Branch, tag, or commit to build
+        #[arg(long, default_value = "main")]
+        checkout: String,
+        /// Path of the contract within the repository
+        #[arg(long, default_value = "")]
+        path: String,
+    },
+    /// Show a verification request, its result, and the builds and
+    /// transactions that handled it
+    Status { id: u64 },
+    /// Build a pending verification request again, forgetting earlier builds
+    Rebuild { id: u64 },
+    /// Register the token or deploy key that CI clones a private repository
+    /// of a pending request with. The registration is signed with the key of
+    /// the requester from a `near login` credentials directory.
+    RegisterCredential {
+        id: u64,
+        /// Account that submitted the request [default: the signer account]
+        #[arg(long)]
+        account: Option<AccountId>,
+        /// File holding an HTTPS access token
+        #[arg(
+            long,
+            required_unless_present = "deploy_key",
+            conflicts_with = "deploy_key"
+        )]
+        token_file: Option<PathBuf>,
+        /// Username to send with the token
+        #[arg(long, requires = "token_file")]
+        username: Option<String>,
+        /// File holding the private key of an SSH deploy key
+        #[arg(long)]
+        deploy_key: Option<PathBuf>,
+        #[arg(long, default_value = "~/.near-credentials")]
+        credentials_dir: PathBuf,
+        /// Base URL of the service [default: derived from the bind address]
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Generate a new ED25519 key pair
+    Keygen,
+    /// Manage passphrase-encrypted keystores
+    Keystore {
+        #[command(subcommand)]
+        command: KeystoreCommand,
+    },
+    /// Check whether a compiled contract has been verified (exits with an
+    /// error if it has not)
+    VerifyWasm { file: PathBuf },
+    /// Check whether the contract deployed to an account has been verified
+    /// (exits with an error if it has not)
+    VerifyAccount {
+        account_id: AccountId,
+        /// Check the contract deployed as of this block height instead,
+        /// read from the archival endpoint of the network
+        #[arg(long)]
+        block: Option<u64>,
+    },
+    /// Inspect and replay resolutions that were given up on
+    DeadLetters {
+        #[command(subcommand)]
+        command: DeadLettersCommand,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum Resolution {
+    /// Record a successful build, using the artifacts of a CircleCI job or
+    /// a JSON file of artifacts (repo, remote, branch, commit, code_url and
+    /// code_hash)
+    Success {
+        id: u64,
+        /// Number of the CircleCI job that built the request
+        #[arg(
+            long,
+            required_unless_present = "artifacts",
+            conflicts_with = "artifacts"
+        )]
+        job: Option<u64>,
+        #[arg(long)]
+        artifacts: Option<PathBuf>,
+    },
+    /// Record a failed build
+    Failure { id: u64 },
+}
+
+#[derive(Subcommand)]
+enum DeadLettersCommand {
+    /// List requests whose resolution was given up on
+    List,
+    /// Retry resolving a request, with a fresh set of attempts
+    Replay { id: u64 },
+}
+
+#[derive(Subcommand)]
+enum KeystoreCommand {
+    /// Create a keystore holding a new key, and print its public key. The
+    /// passphrase is read from KEYSTORE_PASSPHRASE, or prompted for.
+    Create {
+        path: PathBuf,
+        /// Store existing secret keys read from stdin, one per line, instead
+        #[arg(long)]
+        import: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate the configuration and print a summary
+    Check,
+}
+
+impl Command {
+    /// Configuration sections without which the command cannot run, so that
+    /// read-only commands work without the service's secrets.
+    fn required_sections(&self) -> RequiredSections {
+        match self {
+            Self::Serve
+            | Self::Watch { dry_run: false }
+            | Self::Resolve { .. }
+            | Self::Rebuild { .. }
+            | Self::DeadLetters {
+                command: DeadLettersCommand::Replay { .. },
+            }
+            | Self::Config { .. } => RequiredSections::ALL,
+            Self::Request { .. } => RequiredSections::SIGNER,
+            Self::Watch { dry_run: true }
+            | Self::Status { .. }
+            | Self::RegisterCredential { .. }
+            | Self::VerifyWasm { .. }
+            | Self::VerifyAccount { .. }
+            | Self::DeadLetters {
+                command: DeadLettersCommand::List,
+            }
+            | Self::Keygen
+            | Self::Keystore { .. } => RequiredSections::NONE,
+        }
+    }
+}
+
+impl Cli {
+    pub async fn run(self) -> ExitCode {
+        let command = self.command.unwrap_or(Command::Serve);
+
+        // These do not depend on any configuration
+        match command {
+            Command::Keygen => {
+                tools::keygen();
+                return ExitCode::SUCCESS;
+            }
+            Command::Keystore {
+                command: KeystoreCommand::Create { path, import },
+            } => return exit_code(tools::create_keystore(&path, import)),
+            _ => {}
+        }
+
+        let config = match Config::load(self.config.as_deref(), command.required_sections()) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let _telemetry = match telemetry::init(&config.logging) {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let result = match command {
+            Command::Serve => serve::serve(config).await,
+            Command::Watch { dry_run } => registry::watch(config, dry_run).await,
+            Command::Resolve {
+                resolution: Resolution::Success { id, job, artifacts },
+            } => registry::resolve_success(config, id, job, artifacts.as_deref()).await,
+            Command::Resolve {
+                resolution: Resolution::Failure { id },
+            } => registry::resolve_failure(config, id).await,
+            Command::Request {
+                repository,
+                checkout,
+                path,
+            } => registry::request(config, &repository, &checkout, &path).await,
+            Command::Status { id } => registry::status(config, id).await,
+            Command::Rebuild { id } => registry::rebuild(config, id).await,
+            Command::RegisterCredential {
+                id,
+                account,
+                token_file,
+                username,
+                deploy_key,
+                credentials_dir,
+                url,
+            } => {
+                let args = tools::CredentialArgs {
+                    account,
+                    token_file,
+                    username,
+                    deploy_key,
+                    credentials_dir,
+                    url,
+                };
+                tools::register_credential(config, id, args).await
+            }
+            Command::VerifyWasm { file } => tools::verify_wasm(config, &file).await,
+            Command::VerifyAccount { account_id, block } => {
+                tools::verify_account(config, &account_id, block).await
+            }
+            Command::DeadLetters {
+                command: DeadLettersCommand::List,
+            } => registry::list_dead_letters(config),
+            Command::DeadLetters {
+                command: DeadLettersCommand::Replay { id },
+            } => registry::replay(config, id).await,
+            Command::Config {
+                command: ConfigCommand::Check,
+            } => tools::check_config(config).await,
+            Command::Keygen | Command::Keystore { .. } => unreachable!(),
+        };
+
+        exit_code(result)
+    }
+}
+
+fn exit_code(result: Result<(), CliError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use model::verification::VerificationRequest;
+
+use crate::{
+    config::Config,
+    contract_interaction::watch,
+    monitoring::funds::format_near,
+    pipeline::{
+        builds::{Build, BuildTracker},
+        journal::Journal,
+        Artifacts, Correlation, ManualResolution, Pipeline,
+    },
+};
+
+use super::{serve::shutdown_signal, CliError};
+
+fn print_request(request: &VerificationRequest) {
+    println!(
+        "Request {}: {:?}\n\trepository: {}\n\tcheckout: {}\n\tpath: {}\n\tfee: {}",
+        request.id,
+        request.status,
+        request.repository,
+        request.checkout,
+        request.path,
+        format_near(request.fee.0),
+    );
+}
+
+/// Prints new pending requests, or triggers a build for each.
+pub async fn watch(config: Config, dry_run: bool) -> Result<(), CliError> {
+    println!("Watching for pending requests on {}...", config.contract_id);
+
+    if !dry_run {
+        let pipeline = Pipeline::new(&config)?;
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            shutdown_tx.send_replace(true);
+        });
+        pipeline
+            .watch(config.limits.watch_interval, shutdown_rx)
+            .await;
+        return Ok(());
+    }
+
+    let registry = config.registry_client();
+    let mut requests = watch::list::<VerificationRequest, u64>(
+        registry.rpc().clone(),
+        registry.contract_id().clone(),
+        "get_pending_requests".to_string(),
+        serde_json::json!({}),
+        config.limits.watch_interval,
+    );
+
+    while let Some(request) = requests.recv().await {
+        print_request(&request);
+    }
+
+    Ok(())
+}
+
+pub async fn resolve_success(
+    config: Config,
+    id: u64,
+    job: Option<u64>,
+    artifacts: Option<&Path>,
+) -> Result<(), CliError> {
+    let artifacts = match (job, artifacts) {
+        (Some(job), _) => Artifacts::Job(job),
+        (None, Some(path)) => {
+            let text = std::fs::read_to_string(path).map_err(|source| CliError::Read {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            Artifacts::Given(serde_json::from_str(&text).map_err(|e| {
+                CliError::Other(format!("Invalid artifacts in {}: {e}", path.display()))
+            })?)
+        }
+        (None, None) => unreachable!("Required by the argument parser"),
+    };
+
+    let code_hash = Pipeline::new(&config)?
+        .resolve_manually(id, ManualResolution::Success(artifacts))
+        .await?;
+
+    println!("Request {id} resolved as verified with code hash {code_hash}");
+    Ok(())
+}
+
+pub async fn resolve_failure(config: Config, id: u64) -> Result<(), CliError> {
+    Pipeline::new(&config)?
+        .resolve_manually(id, ManualResolution::Failure)
+        .await?;
+
+    println!("Request {id} resolved as failed");
+    Ok(())
+}
+
+pub async fn rebuild(config: Config, id: u64) -> Result<(), CliError> {
+    let commit = Pipeline::new(&config)?.rebuild(id).await?;
+
+    println!("Request {id} is being built again by commit {commit}");
+    Ok(())
+}
+
+pub async fn request(
+    config: Config,
+    repository: &str,
+    checkout: &str,
+    path: &str,
+) -> Result<(), CliError> {
+    let registry = config.registry_client();
+    let fee = registry.get_verification_fee().await?;
+    println!("Verification fee: {}", format_near(fee));
+
+    let request = registry
+        .request_verification(repository, checkout, path, fee)
+        .await?;
+    print_request(&request);
+
+    Ok(())
+}
+
+fn print_build(build: &Build) {
+    println!(
+        "Request {}\n\tcommit: {}\n\tjob: {}\n\tattempts: {}\n\tlast error: {}",
+        build.request.id,
+        build.commit,
+        build
+            .completed_job
+            .as_ref()
+            .map_or_else(String::new, |job| format!(
+                "{} ({})",
+                job.number, job.status
+            )),
+        build.attempts,
+        build.last_error.as_deref().unwrap_or_default(),
+    );
+}
+
+pub async fn status(config: Config, id: u64) -> Result<(), CliError> {
+    let correlation = Correlation::collect(
+        &config.registry_client(),
+        &BuildTracker::open(&config.state_path)?,
+        &Journal::new(&config.journal_path),
+        id,
+    )
+    .await?;
+    print_request(&correlation.request);
+
+    if let Some(verification) = correlation.verification {
+        println!(
+            "Verification:\n\tcode hash: {}\n\tcode url: {}\n\tremote: {}\n\tbranch: {}\n\tcommit: {}",
+            verification.code_hash,
+            verification.code_url,
+            verification.remote,
+            verification.branch,
+            verification.commit,
+        );
+    }
+
+    if let Some(log) = correlation.failure.and_then(|failure| failure.log) {
+        println!(
+            "Build log:\n\thash: {}\n\turl: {}",
+            log.hash,
+            log.url.as_deref().unwrap_or("-"),
+        );
+    }
+
+    if !correlation.builds.is_empty() {
+        println!("Tracked builds:");
+        for build in &correlation.builds {
+            print_build(build);
+        }
+    }
+
+    if !correlation.events.is_empty() {
+        println!("History:");
+        for entry in &correlation.events {
+            println!(
+                "\t{} {}: {}",
+                entry.at,
+                entry.event.name(),
+                serde_json::to_string(&entry.event.kind).unwrap()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list_dead_letters(config: Config) -> Result<(), CliError> {
+    let dead_letters = BuildTracker::open(&config.state_path)?.dead_letters();
+    if dead_letters.is_empty() {
+        println!("No dead letters");
+    }
+
+    for build in &dead_letters {
+        print_build(build);
+    }
+
+    Ok(())
+}
+
+pub async fn replay(config: Config, id: u64) -> Result<(), CliError> {
+    let outcome = Pipeline::new(&config)?.replay(id).await?;
+    println!("{outcome}");
+
+    Ok(())
+}
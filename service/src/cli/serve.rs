@@ -0,0 +1,189 @@
+use std::convert::Infallible;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+use warp::Filter;
+
+use crate::{
+    admin, api,
+    circleci::{signature::verify_filter, webhook},
+    config::Config,
+    contract_interaction::rpc::RpcPool,
+    monitoring::{self, Monitor},
+    pipeline::Pipeline,
+    rejection,
+};
+
+use super::CliError;
+
+fn with<T: Clone + Send>(w: T) -> impl Filter<Extract = (T,), Error = Infallible> + Clone {
+    warp::any().map(move || w.clone())
+}
+
+/// Completes on SIGINT or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Could not listen for SIGINT: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                warn!("Could not listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+pub async fn serve(config: Config) -> Result<(), CliError> {
+    info!(
+        network = %config.network.network_id,
+        node_url = %config.network.node_url,
+        "Connecting"
+    );
+
+    let rpc = RpcPool::new(&config.network);
+    config.network.check_chain_id(&rpc).await?;
+
+    let pipeline = Pipeline::new(&config)?;
+
+    // Before anything can push build branches, since branches are pushed
+    // before their builds are tracked
+    if let Err(e) = pipeline.clean_up_branches().await {
+        warn!("Could not clean up build branches: {e}");
+    }
+
+    let metrics = pipeline.metrics().clone();
+    let rejected = metrics.clone();
+    let guarded = warp::path!("webhook").and(
+        warp::any()
+            .map(move || metrics.webhook_received())
+            .untuple_one()
+            .and(warp::body::content_length_limit(
+                config.limits.webhook_body_limit,
+            ))
+            .and(with(pipeline.clone()))
+            .and(verify_filter(config.circleci().webhook_secrets.clone()))
+            .and_then(webhook::handler)
+            .or_else(move |rejection| {
+                rejected.webhook_rejected();
+                async move { Err(rejection) }
+            }),
+    );
+
+    let api = api::routes(
+        pipeline.registry().clone(),
+        pipeline.events().clone(),
+        pipeline.logs().clone(),
+        config.limits.api_cache_ttl,
+    );
+
+    let monitor = Monitor::new(
+        pipeline.registry().clone(),
+        pipeline.funds().clone(),
+        pipeline.circleci().clone(),
+        pipeline.metrics().clone(),
+    );
+    let monitoring = monitoring::routes(monitor.clone());
+
+    if config.admin_tokens.is_empty() {
+        info!("No admin tokens are configured; the admin API is disabled");
+    }
+    let admin = admin::routes(pipeline.clone(), config.admin_tokens.clone());
+    let access = api::access::routes(pipeline.clone());
+
+    let routes = monitoring
+        .or(admin)
+        .or(access)
+        .or(api)
+        .or(guarded)
+        .recover(rejection::recover)
+        .with(warp::trace::request());
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    let signal = {
+        let mut shutdown = shutdown_rx.clone();
+        async move {
+            let _ = shutdown.changed().await;
+        }
+    };
+    let server = warp::serve(routes);
+    let server = match &config.tls {
+        Some(tls) => {
+            let (address, server) = server
+                .tls()
+                .cert_path(&tls.cert_path)
+                .key_path(&tls.key_path)
+                .bind_with_graceful_shutdown(config.bind_address, signal);
+            info!(%address, "Listening (HTTPS)");
+            tokio::spawn(server)
+        }
+        None => {
+            let (address, server) = server.bind_with_graceful_shutdown(config.bind_address, signal);
+            info!(%address, "Listening");
+            tokio::spawn(server)
+        }
+    };
+
+    // Builds still tracked from before a restart are not triggered again, so
+    // this can run alongside the watcher
+    let retries = tokio::spawn({
+        let pipeline = pipeline.clone();
+        let interval = config.limits.watch_interval;
+        let shutdown = shutdown_rx.clone();
+        async move { pipeline.retry_resolutions(interval, shutdown).await }
+    });
+
+    tokio::spawn({
+        let shutdown = shutdown_rx.clone();
+        async move { monitor.run(shutdown).await }
+    });
+
+    tokio::spawn({
+        let funds = pipeline.funds().clone();
+        let shutdown = shutdown_rx.clone();
+        async move { funds.run(shutdown).await }
+    });
+
+    let watcher = tokio::spawn({
+        let pipeline = pipeline.clone();
+        let interval = config.limits.watch_interval;
+        async move { pipeline.watch(interval, shutdown_rx).await }
+    });
+
+    shutdown_signal().await;
+    info!("Shutting down: no longer accepting requests, draining in-flight work");
+    shutdown_tx.send_replace(true);
+    pipeline.events().close();
+
+    let drained = tokio::time::timeout(config.limits.shutdown_timeout, async {
+        let _ = tokio::join!(server, watcher, retries);
+    })
+    .await;
+
+    match drained {
+        Ok(()) => info!("Shut down cleanly"),
+        Err(_) => warn!(
+            "In-flight work did not finish within {:?}; it will resume on next start",
+            config.limits.shutdown_timeout
+        ),
+    }
+
+    Ok(())
+}
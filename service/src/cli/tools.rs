@@ -0,0 +1,254 @@
+use std::{
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use model::{code_hash::CodeHash, verification::Verification};
+use near_crypto::{KeyType, SecretKey};
+use near_primitives::types::{AccountId, BlockId, BlockReference, Finality};
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    contract_interaction::rpc::RpcPool,
+    env,
+    repository::access::{Registration, RepositoryCredential},
+    signer::{credentials, keystore},
+};
+
+use super::CliError;
+
+pub fn keygen() {
+    let k = SecretKey::from_random(KeyType::ED25519);
+    println!("Secret key: {}", k);
+    println!("Public key: {}", k.public_key());
+}
+
+/// Reads the passphrase for a new keystore from the environment, or prompts
+/// for it twice.
+fn new_passphrase() -> Result<String, CliError> {
+    if let Ok(passphrase) = std::env::var(env::KEYSTORE_PASSPHRASE) {
+        return Ok(passphrase);
+    }
+
+    let prompt = |prompt| {
+        rpassword::prompt_password(prompt)
+            .map_err(|e| CliError::Other(format!("Could not read passphrase: {e}")))
+    };
+    let passphrase = prompt("Passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(CliError::Other(
+            "The passphrase must not be empty".to_string(),
+        ));
+    }
+    if prompt("Repeat passphrase: ")? != passphrase {
+        return Err(CliError::Other("Passphrases do not match".to_string()));
+    }
+
+    Ok(passphrase)
+}
+
+pub fn create_keystore(path: &Path, import: bool) -> Result<(), CliError> {
+    let keys = if import {
+        let mut keys = Vec::new();
+        for line in std::io::stdin().lines() {
+            let line = line.map_err(|e| CliError::Other(format!("Could not read keys: {e}")))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Do not echo the key itself
+            keys.push(
+                SecretKey::from_str(line.trim())
+                    .map_err(|e| CliError::Other(format!("Invalid secret key: {e}")))?,
+            );
+        }
+        if keys.is_empty() {
+            return Err(CliError::Other("No keys were read from stdin".to_string()));
+        }
+        keys
+    } else {
+        vec![SecretKey::from_random(KeyType::ED25519)]
+    };
+
+    keystore::create(path, &keys, &new_passphrase()?)?;
+
+    for key in &keys {
+        println!("Public key: {}", key.public_key());
+    }
+
+    Ok(())
+}
+
+/// Prints where verified code came from, or fails if it is not verified.
+fn report(code_hash: CodeHash, verification: Option<Verification>) -> Result<(), CliError> {
+    println!("Code hash: {code_hash}");
+
+    let verification = verification.ok_or(CliError::Unverified(code_hash))?;
+    println!(
+        "Verified by request {}\n\trepository: {}\n\tbranch: {}\n\tcommit: {}\n\tartifact: {}",
+        verification.request_id,
+        verification.repository,
+        verification.branch,
+        verification.commit,
+        verification.code_url,
+    );
+
+    Ok(())
+}
+
+pub async fn verify_wasm(config: Config, file: &Path) -> Result<(), CliError> {
+    let code = std::fs::read(file).map_err(|source| CliError::Read {
+        path: file.to_path_buf(),
+        source,
+    })?;
+    let code_hash = CodeHash::hash_bytes(&code);
+    let verification = config
+        .registry_client()
+        .verify_code_hash(&code_hash)
+        .await?;
+
+    report(code_hash, verification)
+}
+
+pub async fn verify_account(
+    config: Config,
+    account_id: &AccountId,
+    block: Option<u64>,
+) -> Result<(), CliError> {
+    let block_reference = match block {
+        Some(height) => BlockReference::BlockId(BlockId::Height(height)),
+        None => BlockReference::Finality(Finality::Final),
+    };
+    let (code_hash, verification) = config
+        .registry_client()
+        .verify_account_at(account_id, block_reference)
+        .await?
+        .ok_or_else(|| CliError::Other(format!("No contract is deployed to {account_id}")))?;
+
+    report(code_hash, verification)
+}
+
+pub async fn check_config(config: Config) -> Result<(), CliError> {
+    println!("{}", config.summary());
+
+    let rpc = RpcPool::new(&config.network);
+    config.network.check_chain_id(&rpc).await?;
+
+    println!("Configuration is valid.");
+    Ok(())
+}
+
+pub struct CredentialArgs {
+    pub account: Option<AccountId>,
+    pub token_file: Option<PathBuf>,
+    pub username: Option<String>,
+    pub deploy_key: Option<PathBuf>,
+    pub credentials_dir: PathBuf,
+    pub url: Option<String>,
+}
+
+fn read_secret(path: &Path) -> Result<String, CliError> {
+    std::fs::read_to_string(path).map_err(|source| CliError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// URL this service is reachable at from the same host.
+fn local_url(config: &Config) -> String {
+    let mut address = config.bind_address;
+    if address.ip().is_unspecified() {
+        address.set_ip(Ipv4Addr::LOCALHOST.into());
+    }
+    let scheme = if config.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+
+    format!("{scheme}://{address}")
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RegisteredResponse {
+    rebuild_commit: Option<String>,
+}
+
+pub async fn register_credential(
+    config: Config,
+    id: u64,
+    args: CredentialArgs,
+) -> Result<(), CliError> {
+    let credential = match (&args.token_file, &args.deploy_key) {
+        (Some(path), _) => RepositoryCredential::Token {
+            username: args.username,
+            token: read_secret(path)?.trim().to_string(),
+        },
+        (None, Some(path)) => RepositoryCredential::DeployKey {
+            private_key: read_secret(path)?,
+        },
+        (None, None) => unreachable!("Enforced by the arguments"),
+    };
+
+    let account_id = args
+        .account
+        .or_else(|| config.signer.as_ref().map(|s| s.account_id.clone()))
+        .ok_or_else(|| {
+            CliError::Other("--account is required when no signer is configured".to_string())
+        })?;
+    let secret_key = credentials::load(
+        &args.credentials_dir,
+        &config.network.network_id,
+        &account_id,
+    )
+    .map_err(CliError::Other)?;
+    let message = credential.message(&config.contract_id, id);
+    let registration = Registration {
+        public_key: secret_key.public_key(),
+        signature: secret_key.sign(message.as_bytes()),
+        credential,
+    };
+
+    let url = format!(
+        "{}/requests/{id}/credential",
+        args.url
+            .unwrap_or_else(|| local_url(&config))
+            .trim_end_matches('/')
+    );
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&registration)
+        .send()
+        .await
+        .map_err(|e| CliError::Other(format!("Could not reach {url}: {e}")))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let message = response
+            .json::<ErrorResponse>()
+            .await
+            .map(|e| e.message)
+            .unwrap_or_else(|_| status.to_string());
+        return Err(CliError::Other(format!(
+            "Registration was rejected: {message}"
+        )));
+    }
+
+    println!(
+        "Registered credential for request {id}, signed by {account_id} with {}",
+        registration.public_key
+    );
+    if let Ok(RegisteredResponse {
+        rebuild_commit: Some(commit),
+    }) = response.json().await
+    {
+        println!("Started the build over as {commit}");
+    }
+    Ok(())
+}
@@ -0,0 +1,1237 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use near_crypto::{InMemorySigner, PublicKey, SecretKey};
+use near_primitives::{serialize::from_base64, types::AccountId};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    call_profiles::{self, CallProfile, CallProfiles},
+    circleci::signature::parse_secrets,
+    contract_interaction::{registry::RegistryClient, PollConfig},
+    env,
+    monitoring::funds::{FundsConfig, ONE_NEAR},
+    network_config::{self, NetworkConfig},
+    repository::{
+        access::{self, PrivateRepositoriesConfig},
+        GitCredentials, RepositoryConfig,
+    },
+    signer::{credentials, external::ExternalSigner, keystore, TransactionSigner},
+    telemetry::LoggingConfig,
+};
+
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8000";
+const DEFAULT_STATE_PATH: &str = "state.json";
+const DEFAULT_JOURNAL_PATH: &str = "journal.jsonl";
+const DEFAULT_LOGS_PATH: &str = "logs";
+const DEFAULT_CREDENTIALS_PATH: &str = "credentials.json";
+const DEFAULT_CREDENTIAL_WAIT_SECS: u64 = 120;
+const DEFAULT_REMOTE: &str = "origin";
+const DEFAULT_BRANCH: &str = "main";
+
+/// Configuration file as written by the operator. Every value is optional
+/// here so that it can be supplied by an environment variable instead.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawConfig {
+    contract_id: Option<String>,
+    network: RawNetwork,
+    signer: RawSigner,
+    circleci: RawCircleCi,
+    server: RawServer,
+    repository: RawRepository,
+    state: RawState,
+    admin: RawAdmin,
+    private_repositories: RawPrivateRepositories,
+    limits: RawLimits,
+    funds: RawFunds,
+    logging: RawLogging,
+    call_profiles: HashMap<String, CallProfile>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawNetwork {
+    /// Name of a built-in network
+    name: Option<String>,
+    /// Path to a JSON network config, for custom networks
+    config_path: Option<String>,
+    /// Overrides the chain ID that the RPC node must report
+    chain_id: Option<String>,
+    /// Added to the fallback endpoints of the network
+    fallback_node_urls: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawSigner {
+    account_id: Option<String>,
+    secret_keys: Vec<String>,
+    credentials_dir: Option<PathBuf>,
+    keystore_path: Option<PathBuf>,
+    keystore_passphrase_file: Option<PathBuf>,
+    external: Option<RawExternalSigner>,
+}
+
+impl RawSigner {
+    fn is_set(&self) -> bool {
+        self.account_id.is_some()
+            || !self.secret_keys.is_empty()
+            || self.credentials_dir.is_some()
+            || self.keystore_path.is_some()
+            || self.external.is_some()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawExternalSigner {
+    socket: Option<PathBuf>,
+    public_keys: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawCircleCi {
+    project_slug: Option<String>,
+    api_key: Option<String>,
+    job_name: Option<String>,
+    webhook_secrets: Vec<String>,
+}
+
+impl RawCircleCi {
+    fn is_set(&self) -> bool {
+        self.project_slug.is_some()
+            || self.api_key.is_some()
+            || self.job_name.is_some()
+            || !self.webhook_secrets.is_empty()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawServer {
+    bind_address: Option<String>,
+    public_url: Option<String>,
+    tls_cert_path: Option<PathBuf>,
+    tls_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawRepository {
+    path: Option<PathBuf>,
+    remote: Option<String>,
+    branch: Option<String>,
+    ssh_key_path: Option<PathBuf>,
+    username: Option<String>,
+    token: Option<String>,
+}
+
+impl RawRepository {
+    fn is_set(&self) -> bool {
+        self.path.is_some()
+            || self.remote.is_some()
+            || self.branch.is_some()
+            || self.ssh_key_path.is_some()
+            || self.username.is_some()
+            || self.token.is_some()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawState {
+    path: Option<PathBuf>,
+    journal_path: Option<PathBuf>,
+    logs_path: Option<PathBuf>,
+    credentials_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawAdmin {
+    tokens: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawPrivateRepositories {
+    /// Base64
+    encryption_key: Option<String>,
+    credential_wait_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawLimits {
+    webhook_body_limit: Option<u64>,
+    transaction_timeout_secs: Option<u64>,
+    watch_interval_secs: Option<u64>,
+    api_cache_ttl_secs: Option<u64>,
+    shutdown_timeout_secs: Option<u64>,
+    resolution_attempts: Option<u32>,
+    resolution_backoff_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawFunds {
+    check_interval_secs: Option<u64>,
+    warn_balance_near: Option<f64>,
+    warn_allowance_near: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RawLogging {
+    format: Option<String>,
+    otlp_endpoint: Option<String>,
+}
+
+/// Certificate chain and private key, both PEM-encoded, to serve HTTPS with.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// Keys held by a signing process listening on a Unix socket.
+#[derive(Debug, Clone)]
+pub struct ExternalSignerConfig {
+    pub socket: PathBuf,
+    pub public_keys: Vec<PublicKey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignerConfig {
+    pub account_id: AccountId,
+    /// Keys from `secret_keys`, the credentials directory and the keystore
+    pub secret_keys: Vec<SecretKey>,
+    pub external: Option<ExternalSignerConfig>,
+}
+
+impl SignerConfig {
+    /// One signer per configured key, all for the same account.
+    pub fn signers(&self) -> Vec<TransactionSigner> {
+        let in_memory = self
+            .secret_keys
+            .iter()
+            .map(|k| InMemorySigner::from_secret_key(self.account_id.clone(), k.clone()).into());
+        let external = self.external.iter().flat_map(|external| {
+            external.public_keys.iter().map(|k| {
+                TransactionSigner::External(ExternalSigner::new(
+                    external.socket.clone(),
+                    self.account_id.clone(),
+                    k.clone(),
+                ))
+            })
+        });
+
+        in_memory.chain(external).collect()
+    }
+
+    pub fn key_count(&self) -> usize {
+        self.secret_keys.len() + self.external.as_ref().map_or(0, |e| e.public_keys.len())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CircleCiConfig {
+    pub project_slug: String,
+    pub api_key: String,
+    pub job_name: Option<String>,
+    pub webhook_secrets: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LimitsConfig {
+    pub webhook_body_limit: u64,
+    pub transaction_timeout: Duration,
+    pub watch_interval: Duration,
+    /// How long responses of the public API are cached
+    pub api_cache_ttl: Duration,
+    /// How long to wait for in-flight work on shutdown before exiting anyway.
+    /// Defaults to a little over the transaction timeout, so that a
+    /// resolution that was just sent can still be confirmed.
+    pub shutdown_timeout: Duration,
+    /// Resolutions that fail this many times are moved to the dead letters
+    pub resolution_attempts: u32,
+    /// Delay before the first retry of a failed resolution, doubling after
+    /// each further failure
+    pub resolution_backoff: Duration,
+}
+
+/// Time to finish up after the last transaction is confirmed or given up on.
+const SHUTDOWN_MARGIN: Duration = Duration::from_secs(10);
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_body_limit: 1024 * 32, // 32kb
+            transaction_timeout: PollConfig::default().deadline,
+            watch_interval: Duration::from_secs(10),
+            api_cache_ttl: Duration::from_secs(10),
+            shutdown_timeout: PollConfig::default().deadline + SHUTDOWN_MARGIN,
+            resolution_attempts: 8,
+            resolution_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Sections that a command cannot do without. Sections that are not
+/// required are still validated if any of their values are set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequiredSections {
+    pub signer: bool,
+    pub circleci: bool,
+    pub repository: bool,
+}
+
+impl RequiredSections {
+    /// Read-only commands, which only query the registry and local state
+    pub const NONE: Self = Self {
+        signer: false,
+        circleci: false,
+        repository: false,
+    };
+    /// Commands that send transactions
+    pub const SIGNER: Self = Self {
+        signer: true,
+        ..Self::NONE
+    };
+    /// Commands that build or resolve requests, as the service does
+    pub const ALL: Self = Self {
+        signer: true,
+        circleci: true,
+        repository: true,
+    };
+}
+
+/// Validated service configuration.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub contract_id: AccountId,
+    /// Set if required or configured, see [`RequiredSections`]
+    pub signer: Option<SignerConfig>,
+    pub circleci: Option<CircleCiConfig>,
+    pub bind_address: SocketAddr,
+    /// Base URL of the service in published links, if it is public
+    pub public_url: Option<String>,
+    pub tls: Option<TlsConfig>,
+    pub repository: Option<RepositoryConfig>,
+    pub state_path: PathBuf,
+    pub journal_path: PathBuf,
+    /// Directory of build logs
+    pub logs_path: PathBuf,
+    /// Bearer tokens accepted by the admin API, which is disabled if empty
+    pub admin_tokens: Vec<String>,
+    /// Disabled if `None`
+    pub private_repositories: Option<PrivateRepositoriesConfig>,
+    pub limits: LimitsConfig,
+    pub funds: FundsConfig,
+    pub logging: LoggingConfig,
+    pub call_profiles: CallProfiles,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Could not read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not parse config file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("Invalid configuration:\n{}", .0.iter().map(|e| format!("  - {e}")).collect::<Vec<_>>().join("\n"))]
+    Invalid(Vec<String>),
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Collects validation problems so they can all be reported at once.
+#[derive(Default)]
+struct Problems(Vec<String>);
+
+impl Problems {
+    fn required<T>(&mut self, value: Option<T>, key: &str, env_name: &str) -> Option<T> {
+        if value.is_none() {
+            self.0.push(format!(
+                "`{key}` is required (set it in the config file or with {env_name})"
+            ));
+        }
+        value
+    }
+
+    fn parse<T: FromStr>(&mut self, value: Option<String>, key: &str) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        let value = value?;
+        match value.parse() {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.0.push(format!("`{key}` is invalid ({value:?}): {e}"));
+                None
+            }
+        }
+    }
+
+    /// Trims bearer tokens, which are compared with trimmed headers, and
+    /// reports empty ones.
+    fn tokens(&mut self, tokens: Vec<String>, key: &str) -> Vec<String> {
+        let tokens: Vec<String> = tokens.iter().map(|t| t.trim().to_string()).collect();
+        if tokens.iter().any(String::is_empty) {
+            self.0
+                .push(format!("`{key}` must not contain empty tokens"));
+        }
+        tokens
+    }
+}
+
+impl Config {
+    /// Loads the configuration file (if present) and applies environment
+    /// variable overrides. Fails if a `required` section is missing.
+    ///
+    /// If `path` is `None`, [`DEFAULT_CONFIG_PATH`] is used if it exists.
+    pub fn load(path: Option<&Path>, required: RequiredSections) -> Result<Self, ConfigError> {
+        let path = path.map(Path::to_path_buf).or_else(|| {
+            let default = PathBuf::from(DEFAULT_CONFIG_PATH);
+            default.exists().then_some(default)
+        });
+
+        let raw = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path).map_err(|source| ConfigError::Read {
+                    path: path.clone(),
+                    source,
+                })?;
+                toml::from_str(&text).map_err(|source| ConfigError::Parse { path, source })?
+            }
+            None => RawConfig::default(),
+        };
+
+        Self::from_raw(raw, required)
+    }
+
+    fn from_raw(mut raw: RawConfig, required: RequiredSections) -> Result<Self, ConfigError> {
+        let mut problems = Problems::default();
+
+        // Environment overrides
+        raw.contract_id = env_var(env::CONTRACT_ID).or(raw.contract_id);
+        // Either variable replaces whichever network the file selects
+        match (env_var(env::NETWORK), env_var(env::NETWORK_CONFIG)) {
+            (None, None) => {}
+            (name, config_path) => {
+                raw.network = RawNetwork {
+                    name,
+                    config_path,
+                    ..Default::default()
+                }
+            }
+        }
+        raw.network.chain_id = env_var(env::CHAIN_ID).or(raw.network.chain_id);
+        if let Some(urls) = env_var(env::FALLBACK_NODE_URLS) {
+            raw.network.fallback_node_urls = parse_secrets(&urls);
+        }
+        raw.signer.account_id = env_var(env::ACCOUNT_ID).or(raw.signer.account_id);
+        if let Some(keys) = env_var(env::SECRET_KEY) {
+            raw.signer.secret_keys = parse_secrets(&keys);
+        }
+        raw.signer.credentials_dir = env_var(env::NEAR_CREDENTIALS_DIR)
+            .map(PathBuf::from)
+            .or(raw.signer.credentials_dir);
+        raw.signer.keystore_path = env_var(env::KEYSTORE_PATH)
+            .map(PathBuf::from)
+            .or(raw.signer.keystore_path);
+        if let Some(socket) = env_var(env::EXTERNAL_SIGNER_SOCKET) {
+            raw.signer
+                .external
+                .get_or_insert_with(Default::default)
+                .socket = Some(PathBuf::from(socket));
+        }
+        if let Some(keys) = env_var(env::EXTERNAL_SIGNER_PUBLIC_KEYS) {
+            raw.signer
+                .external
+                .get_or_insert_with(Default::default)
+                .public_keys = parse_secrets(&keys);
+        }
+        raw.circleci.project_slug =
+            env_var(env::CIRCLECI_PROJECT_SLUG).or(raw.circleci.project_slug);
+        raw.circleci.api_key = env_var(env::CIRCLECI_API_KEY).or(raw.circleci.api_key);
+        raw.circleci.job_name = env_var(env::CIRCLECI_JOB_NAME).or(raw.circleci.job_name);
+        if let Some(secrets) = env_var(env::CIRCLECI_WEBHOOK_SECRET) {
+            raw.circleci.webhook_secrets = parse_secrets(&secrets);
+        }
+        if let Some(tokens) = env_var(env::ADMIN_TOKENS) {
+            raw.admin.tokens = parse_secrets(&tokens);
+        }
+        raw.private_repositories.encryption_key =
+            env_var(env::REPOSITORY_CREDENTIALS_KEY).or(raw.private_repositories.encryption_key);
+        raw.state.credentials_path = env_var(env::CREDENTIALS_PATH)
+            .map(PathBuf::from)
+            .or(raw.state.credentials_path);
+        raw.server.bind_address = env_var(env::BIND_ADDRESS).or(raw.server.bind_address);
+        raw.server.public_url = env_var(env::PUBLIC_URL).or(raw.server.public_url);
+        raw.server.tls_cert_path = env_var(env::TLS_CERT_PATH)
+            .map(PathBuf::from)
+            .or(raw.server.tls_cert_path);
+        raw.server.tls_key_path = env_var(env::TLS_KEY_PATH)
+            .map(PathBuf::from)
+            .or(raw.server.tls_key_path);
+        raw.repository.path = env_var(env::REPOSITORY_PATH)
+            .map(PathBuf::from)
+            .or(raw.repository.path);
+        raw.repository.remote = env_var(env::REPOSITORY_REMOTE).or(raw.repository.remote);
+        raw.repository.branch = env_var(env::REPOSITORY_BRANCH).or(raw.repository.branch);
+        raw.repository.ssh_key_path = env_var(env::GIT_SSH_KEY_PATH)
+            .map(PathBuf::from)
+            .or(raw.repository.ssh_key_path);
+        raw.repository.username = env_var(env::GIT_USERNAME).or(raw.repository.username);
+        raw.repository.token = env_var(env::GIT_TOKEN).or(raw.repository.token);
+
+        let mut network = match (raw.network.name, raw.network.config_path) {
+            (Some(_), Some(_)) => {
+                problems.0.push(
+                    "Only one of `network.name` and `network.config_path` may be set".to_string(),
+                );
+                None
+            }
+            (Some(name), None) => {
+                let preset = network_config::preset(&name);
+                if preset.is_none() {
+                    problems.0.push(format!(
+                        "`network.name` ({name:?}) is not one of {}",
+                        network_config::PRESETS.join(", ")
+                    ));
+                }
+                preset
+            }
+            (None, Some(path)) => network_config::load(&path)
+                .map_err(|e| problems.0.push(e))
+                .ok(),
+            (None, None) => {
+                problems.0.push(format!(
+                    "`network.name` or `network.config_path` is required (set it in the config file or with {} or {})",
+                    env::NETWORK,
+                    env::NETWORK_CONFIG
+                ));
+                None
+            }
+        };
+
+        if let Some(network) = network.as_mut() {
+            if let Some(chain_id) = raw.network.chain_id {
+                network.chain_id = Some(chain_id);
+            }
+            for url in raw.network.fallback_node_urls {
+                match url.parse::<reqwest::Url>() {
+                    Ok(_) if !network.rpc_urls().any(|u| u == url) => {
+                        network.fallback_node_urls.push(url)
+                    }
+                    Ok(_) => {}
+                    Err(e) => problems.0.push(format!(
+                        "`network.fallback_node_urls` contains an invalid URL ({url:?}): {e}"
+                    )),
+                }
+            }
+        }
+
+        let contract_id = problems
+            .required(raw.contract_id, "contract_id", env::CONTRACT_ID)
+            .and_then(|id| problems.parse::<AccountId>(Some(id), "contract_id"));
+
+        let needs_signer = required.signer || raw.signer.is_set();
+        let needs_circleci = required.circleci || raw.circleci.is_set();
+        let needs_repository = required.repository || raw.repository.is_set();
+
+        let account_id = if needs_signer {
+            problems
+                .required(raw.signer.account_id, "signer.account_id", env::ACCOUNT_ID)
+                .and_then(|id| problems.parse::<AccountId>(Some(id), "signer.account_id"))
+        } else {
+            None
+        };
+
+        let mut secret_keys = raw
+            .signer
+            .secret_keys
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, key)| {
+                SecretKey::from_str(&key)
+                    .map_err(|e| {
+                        // Do not echo the key itself
+                        problems
+                            .0
+                            .push(format!("`signer.secret_keys[{i}]` is invalid: {e}"))
+                    })
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        if let (Some(dir), Some(network), Some(account_id)) =
+            (&raw.signer.credentials_dir, &network, &account_id)
+        {
+            match credentials::load(dir, &network.network_id, account_id) {
+                Ok(key) => secret_keys.push(key),
+                Err(e) => problems.0.push(format!("`signer.credentials_dir`: {e}")),
+            }
+        }
+
+        if let Some(path) = &raw.signer.keystore_path {
+            let passphrase = match (
+                env_var(env::KEYSTORE_PASSPHRASE),
+                &raw.signer.keystore_passphrase_file,
+            ) {
+                (Some(passphrase), _) => Some(passphrase),
+                (None, Some(file)) => std::fs::read_to_string(file)
+                    .map(|p| p.trim_end_matches(['\r', '\n']).to_string())
+                    .map_err(|e| {
+                        problems.0.push(format!(
+                            "Could not read `signer.keystore_passphrase_file` ({}): {e}",
+                            file.display()
+                        ))
+                    })
+                    .ok(),
+                (None, None) => {
+                    problems.0.push(format!(
+                        "`signer.keystore_path` requires a passphrase (set {} or `signer.keystore_passphrase_file`)",
+                        env::KEYSTORE_PASSPHRASE
+                    ));
+                    None
+                }
+            };
+            if let Some(passphrase) = passphrase {
+                match keystore::read(path, &passphrase) {
+                    Ok(keys) => secret_keys.extend(keys),
+                    Err(e) => problems.0.push(format!(
+                        "Could not open `signer.keystore_path` ({}): {e}",
+                        path.display()
+                    )),
+                }
+            }
+        }
+
+        let external = raw.signer.external.and_then(|external| {
+            let socket = problems.required(
+                external.socket,
+                "signer.external.socket",
+                env::EXTERNAL_SIGNER_SOCKET,
+            );
+            if external.public_keys.is_empty() {
+                problems.0.push(format!(
+                    "`signer.external.public_keys` requires at least one key (set it in the config file or with {})",
+                    env::EXTERNAL_SIGNER_PUBLIC_KEYS
+                ));
+            }
+            let public_keys = external
+                .public_keys
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, key)| {
+                    problems.parse::<PublicKey>(Some(key), &format!("signer.external.public_keys[{i}]"))
+                })
+                .collect();
+            Some(ExternalSignerConfig {
+                socket: socket?,
+                public_keys,
+            })
+        });
+
+        if needs_signer && secret_keys.is_empty() && external.is_none() {
+            problems.0.push(format!(
+                "`signer` requires at least one key (set `signer.secret_keys` or {}, `signer.credentials_dir`, `signer.keystore_path` or `signer.external`)",
+                env::SECRET_KEY
+            ));
+        }
+
+        let circleci = if needs_circleci {
+            let project_slug = problems.required(
+                raw.circleci.project_slug,
+                "circleci.project_slug",
+                env::CIRCLECI_PROJECT_SLUG,
+            );
+            let api_key = problems.required(
+                raw.circleci.api_key,
+                "circleci.api_key",
+                env::CIRCLECI_API_KEY,
+            );
+            if raw.circleci.webhook_secrets.is_empty() {
+                problems.0.push(format!(
+                    "`circleci.webhook_secrets` requires at least one secret (set it in the config file or with {})",
+                    env::CIRCLECI_WEBHOOK_SECRET
+                ));
+            }
+            project_slug
+                .zip(api_key)
+                .map(|(project_slug, api_key)| CircleCiConfig {
+                    project_slug,
+                    api_key,
+                    job_name: raw.circleci.job_name,
+                    webhook_secrets: raw.circleci.webhook_secrets,
+                })
+        } else {
+            None
+        };
+        let private_repositories = match raw.private_repositories.encryption_key {
+            Some(key) => {
+                let encryption_key = from_base64(&key)
+                    .ok()
+                    .and_then(|key| <[u8; access::KEY_LEN]>::try_from(key).ok());
+                if encryption_key.is_none() {
+                    problems.0.push(format!(
+                        "`private_repositories.encryption_key` must be {} bytes, base64-encoded",
+                        access::KEY_LEN
+                    ));
+                }
+                encryption_key.map(|encryption_key| PrivateRepositoriesConfig {
+                    encryption_key,
+                    credentials_path: raw
+                        .state
+                        .credentials_path
+                        .unwrap_or_else(|| PathBuf::from(DEFAULT_CREDENTIALS_PATH)),
+                    credential_wait: Duration::from_secs(
+                        raw.private_repositories
+                            .credential_wait_secs
+                            .unwrap_or(DEFAULT_CREDENTIAL_WAIT_SECS),
+                    ),
+                })
+            }
+            None => None,
+        };
+
+        let admin_tokens = problems.tokens(raw.admin.tokens, "admin.tokens");
+
+        let mut bind_address = problems.parse::<SocketAddr>(
+            Some(
+                raw.server
+                    .bind_address
+                    .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string()),
+            ),
+            "server.bind_address",
+        );
+        if let (Some(addr), Some(port)) = (
+            bind_address.as_mut(),
+            problems.parse::<u16>(env_var(env::PORT), env::PORT),
+        ) {
+            addr.set_port(port);
+        }
+
+        if let Some(url) = &raw.server.public_url {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                problems.0.push(format!(
+                    "`server.public_url` ({url}) must be an HTTP(S) URL"
+                ));
+            }
+        }
+
+        let tls = match (raw.server.tls_cert_path, raw.server.tls_key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                for (path, field) in [
+                    (&cert_path, "server.tls_cert_path"),
+                    (&key_path, "server.tls_key_path"),
+                ] {
+                    if !path.is_file() {
+                        problems
+                            .0
+                            .push(format!("`{field}` ({}) does not exist", path.display()));
+                    }
+                }
+                Some(TlsConfig {
+                    cert_path,
+                    key_path,
+                })
+            }
+            (None, None) => None,
+            _ => {
+                problems.0.push(
+                    "`server.tls_cert_path` and `server.tls_key_path` must be set together"
+                        .to_string(),
+                );
+                None
+            }
+        };
+
+        let repository_path = if needs_repository {
+            problems.required(raw.repository.path, "repository.path", env::REPOSITORY_PATH)
+        } else {
+            None
+        };
+        let remote = raw
+            .repository
+            .remote
+            .unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+        let branch = raw
+            .repository
+            .branch
+            .unwrap_or_else(|| DEFAULT_BRANCH.to_string());
+        if !git2::Reference::is_valid_name(&format!("refs/heads/{branch}")) {
+            problems.0.push(format!(
+                "`repository.branch` ({branch}) is not a valid branch name"
+            ));
+        }
+        if let Some(path) = &repository_path {
+            match git2::Repository::open(path) {
+                Ok(repository) => {
+                    if repository.find_remote(&remote).is_err() {
+                        problems.0.push(format!(
+                            "`repository.path` ({}) has no remote named `{remote}`",
+                            path.display()
+                        ));
+                    }
+                }
+                Err(_) => problems.0.push(format!(
+                    "`repository.path` ({}) is not a git repository",
+                    path.display()
+                )),
+            }
+        }
+        if let Some(key) = &raw.repository.ssh_key_path {
+            if !credentials::expand_home(key).is_file() {
+                problems.0.push(format!(
+                    "`repository.ssh_key_path` ({}) does not exist",
+                    key.display()
+                ));
+            }
+        }
+
+        let defaults = LimitsConfig::default();
+        let transaction_timeout = raw
+            .limits
+            .transaction_timeout_secs
+            .map_or(defaults.transaction_timeout, Duration::from_secs);
+        let limits = LimitsConfig {
+            webhook_body_limit: raw
+                .limits
+                .webhook_body_limit
+                .unwrap_or(defaults.webhook_body_limit),
+            transaction_timeout,
+            watch_interval: raw
+                .limits
+                .watch_interval_secs
+                .map_or(defaults.watch_interval, Duration::from_secs),
+            api_cache_ttl: raw
+                .limits
+                .api_cache_ttl_secs
+                .map_or(defaults.api_cache_ttl, Duration::from_secs),
+            shutdown_timeout: raw
+                .limits
+                .shutdown_timeout_secs
+                .map_or(transaction_timeout + SHUTDOWN_MARGIN, Duration::from_secs),
+            resolution_attempts: raw
+                .limits
+                .resolution_attempts
+                .unwrap_or(defaults.resolution_attempts),
+            resolution_backoff: raw
+                .limits
+                .resolution_backoff_secs
+                .map_or(defaults.resolution_backoff, Duration::from_secs),
+        };
+        if limits.webhook_body_limit == 0 {
+            problems
+                .0
+                .push("`limits.webhook_body_limit` must be greater than 0".to_string());
+        }
+        if limits.resolution_attempts == 0 {
+            problems
+                .0
+                .push("`limits.resolution_attempts` must be greater than 0".to_string());
+        }
+        if limits.watch_interval.is_zero() {
+            problems
+                .0
+                .push("`limits.watch_interval_secs` must be greater than 0".to_string());
+        }
+
+        let funds_defaults = FundsConfig::default();
+        let near_threshold = |value: Option<f64>, key: &str, problems: &mut Problems| match value {
+            Some(near) if near.is_finite() && near >= 0.0 => Some((near * ONE_NEAR as f64) as u128),
+            Some(near) => {
+                problems.0.push(format!(
+                    "`{key}` ({near}) must be a non-negative amount of NEAR"
+                ));
+                None
+            }
+            None => None,
+        };
+        let funds = FundsConfig {
+            check_interval: raw
+                .funds
+                .check_interval_secs
+                .map_or(funds_defaults.check_interval, Duration::from_secs),
+            warn_balance: near_threshold(
+                raw.funds.warn_balance_near,
+                "funds.warn_balance_near",
+                &mut problems,
+            )
+            .unwrap_or(funds_defaults.warn_balance),
+            warn_allowance: near_threshold(
+                raw.funds.warn_allowance_near,
+                "funds.warn_allowance_near",
+                &mut problems,
+            )
+            .unwrap_or(funds_defaults.warn_allowance),
+        };
+        if funds.check_interval.is_zero() {
+            problems
+                .0
+                .push("`funds.check_interval_secs` must be greater than 0".to_string());
+        }
+
+        let logging = LoggingConfig {
+            format: problems
+                .parse(
+                    env_var(env::LOG_FORMAT).or(raw.logging.format),
+                    "logging.format",
+                )
+                .unwrap_or_default(),
+            otlp_endpoint: env_var(env::OTEL_EXPORTER_OTLP_ENDPOINT).or(raw.logging.otlp_endpoint),
+        };
+        if logging.otlp_endpoint.is_some() && !cfg!(feature = "otel") {
+            problems.0.push(
+                "`logging.otlp_endpoint` requires the service to be built with the `otel` feature"
+                    .to_string(),
+            );
+        }
+
+        let call_profiles = match env_var(env::CALL_PROFILES) {
+            Some(path) => call_profiles::load(&path)
+                .map_err(|e| problems.0.push(e))
+                .ok(),
+            None => Some(CallProfiles::from(raw.call_profiles)),
+        };
+
+        if !problems.0.is_empty() {
+            return Err(ConfigError::Invalid(problems.0));
+        }
+
+        // All values are present if no problems were recorded
+        Ok(Self {
+            network: network.unwrap(),
+            contract_id: contract_id.unwrap(),
+            signer: account_id.map(|account_id| SignerConfig {
+                account_id,
+                secret_keys,
+                external,
+            }),
+            circleci,
+            bind_address: bind_address.unwrap(),
+            public_url: raw.server.public_url,
+            tls,
+            repository: repository_path.map(|path| RepositoryConfig {
+                path,
+                remote,
+                branch,
+                credentials: GitCredentials {
+                    ssh_key_path: raw.repository.ssh_key_path,
+                    ssh_key_passphrase: env_var(env::GIT_SSH_KEY_PASSPHRASE),
+                    username: raw.repository.username,
+                    token: raw.repository.token,
+                },
+            }),
+            state_path: env_var(env::STATE_PATH)
+                .map(PathBuf::from)
+                .or(raw.state.path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_STATE_PATH)),
+            journal_path: env_var(env::JOURNAL_PATH)
+                .map(PathBuf::from)
+                .or(raw.state.journal_path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_JOURNAL_PATH)),
+            logs_path: env_var(env::LOGS_PATH)
+                .map(PathBuf::from)
+                .or(raw.state.logs_path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_LOGS_PATH)),
+            admin_tokens,
+            private_repositories,
+            limits,
+            funds,
+            logging,
+            call_profiles: call_profiles.unwrap(),
+        })
+    }
+
+    pub fn poll_config(&self) -> PollConfig {
+        PollConfig {
+            deadline: self.limits.transaction_timeout,
+            ..PollConfig::default()
+        }
+    }
+
+    /// # Panics
+    ///
+    /// If the configuration was loaded without requiring the signer.
+    pub fn signer(&self) -> &SignerConfig {
+        self.signer
+            .as_ref()
+            .expect("The signer is required by the command")
+    }
+
+    /// # Panics
+    ///
+    /// If the configuration was loaded without requiring CircleCI.
+    pub fn circleci(&self) -> &CircleCiConfig {
+        self.circleci
+            .as_ref()
+            .expect("CircleCI is required by the command")
+    }
+
+    /// # Panics
+    ///
+    /// If the configuration was loaded without requiring the repository.
+    pub fn repository(&self) -> &RepositoryConfig {
+        self.repository
+            .as_ref()
+            .expect("The repository is required by the command")
+    }
+
+    /// Registry client that signs with the configured keys, or a read-only
+    /// client if no signer is configured.
+    pub fn registry_client(&self) -> RegistryClient {
+        let client = RegistryClient::new(self.network.clone(), self.contract_id.clone())
+            .with_poll_config(self.poll_config())
+            .with_call_profiles(self.call_profiles.clone());
+
+        match &self.signer {
+            Some(signer) => client.with_signers(signer.signers()),
+            None => client,
+        }
+    }
+
+    /// Human-readable summary with secrets redacted.
+    pub fn summary(&self) -> String {
+        format!(
+            "network: {} ({})\n\
+             contract: {}\n\
+             signer: {}\n\
+             circleci: {}\n\
+             bind address: {} ({})\n\
+             public URL: {}\n\
+             repository: {}\n\
+             state: {}\n\
+             journal: {}\n\
+             build logs: {}\n\
+             admin tokens: {}\n\
+             private repositories: {}\n\
+             limits: {:?}",
+            self.network.network_id,
+            self.network.rpc_urls().collect::<Vec<_>>().join(", "),
+            self.contract_id,
+            match &self.signer {
+                Some(signer) => format!("{} ({} key(s))", signer.account_id, signer.key_count()),
+                None => "none".to_string(),
+            },
+            match &self.circleci {
+                Some(circleci) => format!(
+                    "{} ({} webhook secret(s))",
+                    circleci.project_slug,
+                    circleci.webhook_secrets.len()
+                ),
+                None => "none".to_string(),
+            },
+            self.bind_address,
+            if self.tls.is_some() { "https" } else { "http" },
+            self.public_url.as_deref().unwrap_or("none"),
+            match &self.repository {
+                Some(repository) => format!(
+                    "{} ({} {}, {})",
+                    repository.path.display(),
+                    repository.remote,
+                    repository.branch,
+                    match &repository.credentials {
+                        GitCredentials { token: Some(_), .. } => "token",
+                        GitCredentials {
+                            ssh_key_path: Some(_),
+                            ..
+                        } => "ssh key",
+                        _ => "ssh agent or no credentials",
+                    }
+                ),
+                None => "none".to_string(),
+            },
+            self.state_path.display(),
+            self.journal_path.display(),
+            self.logs_path.display(),
+            self.admin_tokens.len(),
+            match &self.private_repositories {
+                Some(private) => format!("enabled ({})", private.credentials_path.display()),
+                None => "disabled".to_string(),
+            },
+            self.limits,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ConfigError, LimitsConfig, RawConfig, RequiredSections};
+
+    #[test]
+    fn reports_all_problems() {
+        let raw: RawConfig = toml::from_str(
+            r#"
+            contract_id = "Not An Account"
+
+            [network]
+            name = "betanet"
+
+            [server]
+            bind_address = "nowhere"
+            tls_cert_path = "cert.pem"
+            "#,
+        )
+        .unwrap();
+
+        match Config::from_raw(raw, RequiredSections::ALL) {
+            Err(ConfigError::Invalid(problems)) => {
+                let joined = problems.join("\n");
+                assert!(joined.contains("`contract_id` is invalid"));
+                assert!(joined.contains("`server.bind_address` is invalid"));
+                assert!(joined.contains("must be set together"));
+                assert!(joined.contains("`network.name` (\"betanet\") is not one of"));
+                assert!(joined.contains("`signer` requires at least one key"));
+            }
+            other => panic!("Expected invalid config, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn requires_sections_per_command() {
+        let raw = || -> RawConfig {
+            toml::from_str(
+                r#"
+                contract_id = "registry.testnet"
+
+                [network]
+                name = "testnet"
+                "#,
+            )
+            .unwrap()
+        };
+
+        let config = Config::from_raw(raw(), RequiredSections::NONE).unwrap();
+        assert!(config.signer.is_none() && config.circleci.is_none());
+        assert!(config.repository.is_none());
+
+        match Config::from_raw(raw(), RequiredSections::SIGNER) {
+            Err(ConfigError::Invalid(problems)) => {
+                let joined = problems.join("\n");
+                assert!(joined.contains("`signer.account_id` is required"));
+                assert!(!joined.contains("circleci"));
+            }
+            other => panic!("Expected invalid config, got {other:?}"),
+        }
+
+        // Sections that are set are validated even if not required
+        let mut partial = raw();
+        partial.circleci.job_name = Some("build".to_string());
+        assert!(Config::from_raw(partial, RequiredSections::NONE).is_err());
+    }
+
+    #[test]
+    fn trims_tokens() {
+        let raw = |admin_tokens: &str| -> RawConfig {
+            toml::from_str(&format!(
+                r#"
+                contract_id = "registry.testnet"
+
+                [network]
+                name = "testnet"
+
+                [admin]
+                tokens = {admin_tokens}
+                "#
+            ))
+            .unwrap()
+        };
+
+        let config =
+            Config::from_raw(raw(r#"[" admin-secret\n"]"#), RequiredSections::NONE).unwrap();
+        assert_eq!(config.admin_tokens, ["admin-secret"]);
+
+        match Config::from_raw(raw(r#"["admin-secret", " "]"#), RequiredSections::NONE) {
+            Err(ConfigError::Invalid(problems)) => {
+                assert_eq!(problems, ["`admin.tokens` must not contain empty tokens"]);
+            }
+            other => panic!("Expected invalid config, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn shutdown_waits_for_transactions() {
+        let raw: RawConfig = toml::from_str(
+            r#"
+            contract_id = "registry.testnet"
+
+            [network]
+            name = "testnet"
+
+            [limits]
+            transaction_timeout_secs = 300
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::from_raw(raw, RequiredSections::NONE).unwrap();
+        assert!(config.limits.shutdown_timeout > config.limits.transaction_timeout);
+        let defaults = LimitsConfig::default();
+        assert!(defaults.shutdown_timeout > defaults.transaction_timeout);
+    }
+
+    #[test]
+    fn adds_fallback_node_urls_to_presets() {
+        let raw = |urls: &str| -> RawConfig {
+            toml::from_str(&format!(
+                r#"
+                contract_id = "registry.testnet"
+
+                [network]
+                name = "testnet"
+                fallback_node_urls = {urls}
+                "#
+            ))
+            .unwrap()
+        };
+
+        let config = Config::from_raw(
+            raw(r#"["https://rpc.testnet.example.com"]"#),
+            RequiredSections::NONE,
+        )
+        .unwrap();
+        assert_eq!(
+            config.network.rpc_urls().collect::<Vec<_>>(),
+            [
+                config.network.node_url.as_str(),
+                "https://rpc.testnet.example.com"
+            ]
+        );
+
+        match Config::from_raw(raw(r#"["not a url"]"#), RequiredSections::NONE) {
+            Err(ConfigError::Invalid(problems)) => {
+                assert!(
+                    problems[0].contains("`network.fallback_node_urls` contains an invalid URL")
+                );
+            }
+            other => panic!("Expected invalid config, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(toml::from_str::<RawConfig>("contract = \"registry.testnet\"").is_err());
+    }
+}
@@ -1,45 +1,142 @@
-use near_crypto::{InMemorySigner, Signer};
-use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_jsonrpc_client::{
+    errors::{JsonRpcError, JsonRpcServerError},
+    methods::{self, broadcast_tx_commit::RpcTransactionError},
+};
 
 use near_primitives::{
+    errors::InvalidTxError,
+    hash::CryptoHash,
     transaction::{Action, FunctionCallAction, Transaction},
-    types::AccountId,
+    types::{AccountId, Balance, Gas},
+    views::{FinalExecutionOutcomeView, FinalExecutionStatus},
+};
+
+use tracing::{debug, info, warn, Span};
+
+use crate::call_profiles::CallProfile;
+
+use super::{
+    nonce::NonceManager, rpc::RpcPool, wait_for_status, ContractInteractionError, PollConfig,
+    SentTransaction,
 };
 
-use super::{valid_for, wait_for_status};
+/// Number of times a transaction is rebuilt with a fresh nonce after the node
+/// rejects its nonce.
+const MAX_NONCE_RETRIES: usize = 3;
+
+/// Result of a successful change call.
+#[derive(Debug, Clone)]
+pub struct ChangeOutcome {
+    pub transaction_hash: CryptoHash,
+    /// Base64-encoded return value
+    pub value: String,
+    /// Gas burnt by the transaction and all of its receipts
+    pub gas_burnt: Gas,
+    /// Tokens burnt by the transaction and all of its receipts
+    pub tokens_burnt: Balance,
+}
+
+impl From<FinalExecutionOutcomeView> for ChangeOutcome {
+    fn from(outcome: FinalExecutionOutcomeView) -> Self {
+        let all_outcomes = || {
+            std::iter::once(&outcome.transaction_outcome.outcome)
+                .chain(outcome.receipts_outcome.iter().map(|r| &r.outcome))
+        };
+
+        Self {
+            transaction_hash: outcome.transaction_outcome.id,
+            value: match &outcome.status {
+                FinalExecutionStatus::SuccessValue(s) => s.clone(),
+                _ => String::new(),
+            },
+            gas_burnt: all_outcomes().map(|o| o.gas_burnt).sum(),
+            tokens_burnt: all_outcomes().map(|o| o.tokens_burnt).sum(),
+        }
+    }
+}
 
+#[tracing::instrument(skip_all, fields(%method, %contract_id, tx_hash))]
 pub async fn change(
-    client: &JsonRpcClient,
-    signer: &InMemorySigner,
+    rpc: &RpcPool,
+    nonces: &NonceManager,
     contract_id: &AccountId,
     method: &str,
     args: serde_json::Value,
-    deposit: u128,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let (nonce, block_hash) = valid_for(client, signer).await?;
-
-    let tx = Transaction {
-        signer_id: signer.account_id.clone(),
-        public_key: signer.public_key.clone(),
-        nonce: nonce + 1,
-        receiver_id: contract_id.clone(),
-        block_hash,
-        actions: vec![Action::FunctionCall(FunctionCallAction {
-            method_name: method.to_string(),
-            args: args.to_string().into_bytes(),
-            gas: 100_000_000_000_000, // 100 TGas
-            deposit,
-        })],
-    };
-
-    println!("Signing with {}", signer.public_key());
-    let request = methods::broadcast_tx_async::RpcBroadcastTxAsyncRequest {
-        signed_transaction: tx.sign(signer),
-    };
-
-    let tx_hash = client.call(request).await?;
-
-    println!("Sent transaction {}", tx_hash);
-
-    wait_for_status(client, &signer.account_id, tx_hash).await
+    profile: CallProfile,
+    poll_config: &PollConfig,
+) -> Result<ChangeOutcome, ContractInteractionError> {
+    let mut attempt = 0;
+
+    loop {
+        let lease = nonces.next(profile.deposit).await?;
+
+        let tx = Transaction {
+            signer_id: lease.signer.account_id().clone(),
+            public_key: lease.signer.public_key().clone(),
+            nonce: lease.nonce,
+            receiver_id: contract_id.clone(),
+            block_hash: lease.block_hash,
+            actions: vec![Action::FunctionCall(FunctionCallAction {
+                method_name: method.to_string(),
+                args: args.to_string().into_bytes(),
+                gas: profile.gas,
+                deposit: profile.deposit,
+            })],
+        };
+
+        debug!(
+            public_key = %lease.signer.public_key(),
+            nonce = lease.nonce,
+            "Signing transaction"
+        );
+        // Unlike broadcast_tx_async, this reports validation errors (e.g.
+        // invalid nonce) instead of silently dropping the transaction.
+        let request = methods::EXPERIMENTAL_broadcast_tx_sync::RpcBroadcastTxSyncRequest {
+            signed_transaction: lease.signer.sign(tx).await?,
+        };
+
+        let response = match rpc.call(request).await {
+            Ok(response) => response,
+            Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::InvalidTransaction {
+                    context:
+                        context @ (InvalidTxError::InvalidNonce { .. }
+                        | InvalidTxError::NonceTooLarge { .. }),
+                },
+            ))) if attempt < MAX_NONCE_RETRIES => {
+                warn!(%context, "Nonce rejected, resyncing");
+                let ak_nonce = match context {
+                    InvalidTxError::InvalidNonce { ak_nonce, .. } => Some(ak_nonce),
+                    _ => None,
+                };
+                nonces.resync(lease.signer.public_key(), ak_nonce).await;
+                attempt += 1;
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Span::current().record(
+            "tx_hash",
+            tracing::field::display(&response.transaction_hash),
+        );
+        info!("Sent transaction");
+
+        let transaction = SentTransaction {
+            hash: response.transaction_hash,
+            signer_id: lease.signer.account_id().clone(),
+            reference_block_hash: lease.block_hash,
+        };
+        let outcome: ChangeOutcome = wait_for_status(rpc, &transaction, poll_config)
+            .await?
+            .into();
+
+        info!(
+            gas_burnt = outcome.gas_burnt,
+            tokens_burnt = %outcome.tokens_burnt,
+            "Transaction completed"
+        );
+
+        return Ok(outcome);
+    }
 }
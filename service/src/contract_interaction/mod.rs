@@ -1,113 +1,361 @@
-use std::time;
+use std::time::{Duration, Instant};
 
-use near_crypto::{InMemorySigner, Signer};
 use near_jsonrpc_client::{
+    errors::{JsonRpcError, JsonRpcServerError},
     methods::{
-        self, broadcast_tx_commit::RpcTransactionError, query::RpcQueryRequest, tx::TransactionInfo, RpcHandlerError,
+        self, block::RpcBlockError, broadcast_tx_commit::RpcTransactionError,
+        gas_price::RpcGasPriceError, query::RpcQueryError, query::RpcQueryRequest,
+        tx::TransactionInfo, EXPERIMENTAL_genesis_config::RpcGenesisConfigError,
     },
-    JsonRpcClient,
 };
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
 
 use near_primitives::{
+    errors::TxExecutionError,
     hash::CryptoHash,
-    types::{AccountId, BlockReference},
-    views::{FinalExecutionOutcomeView, FinalExecutionStatus, QueryRequest},
+    types::{AccountId, BlockHeightDelta, BlockId, BlockReference},
+    views::{
+        AccessKeyPermissionView, FinalExecutionOutcomeView, FinalExecutionStatus, QueryRequest,
+    },
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::signer::{SignerError, TransactionSigner};
+
+use self::rpc::RpcPool;
+
 pub mod change;
+pub mod nonce;
+pub mod registry;
+pub mod rpc;
 pub mod view;
 pub mod watch;
 
-#[derive(Error, Debug)]
-pub enum TransactionStatusError {
-    #[error("JSON RPC error: {0}")]
-    JsonRpcError(#[from] near_jsonrpc_client::errors::JsonRpcError<RpcTransactionError>),
-    // #[error("RPC Handler Error: {0}")]
-    // RpcHandlerError(#[from] Box<dyn RpcHandlerError>),
+/// A transaction that was sent, whose outcome can be looked up again later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentTransaction {
+    pub hash: CryptoHash,
+    pub signer_id: AccountId,
+    /// Block hash the transaction was signed with
+    pub reference_block_hash: CryptoHash,
+}
+
+/// The part of the genesis configuration that is needed here.
+#[derive(Debug, Deserialize)]
+struct GenesisConfig {
+    /// Number of blocks after its reference block during which a transaction
+    /// may be included
+    transaction_validity_period: BlockHeightDelta,
+}
+
+impl methods::RpcHandlerResponse for GenesisConfig {}
+
+#[derive(Debug, Error)]
+pub enum ContractInteractionError {
+    #[error("RPC query error: {0}")]
+    Query(#[from] JsonRpcError<RpcQueryError>),
+    #[error("RPC transaction error: {0}")]
+    Transaction(#[from] JsonRpcError<RpcTransactionError>),
+    #[error("RPC block error: {0}")]
+    Block(#[from] JsonRpcError<RpcBlockError>),
+    #[error("RPC gas price error: {0}")]
+    GasPrice(#[from] JsonRpcError<RpcGasPriceError>),
+    #[error("RPC genesis config error: {0}")]
+    Genesis(#[from] JsonRpcError<RpcGenesisConfigError>),
     #[error("Execution error: {0}")]
-    ExecutionError(near_primitives::errors::TxExecutionError),
+    Execution(TxExecutionError),
+    /// The transaction may still be included, so its outcome is unknown
+    #[error("Transaction {} did not complete within {elapsed:?}", transaction.hash)]
+    Timeout {
+        transaction: SentTransaction,
+        elapsed: Duration,
+    },
+    #[error("Transaction {hash} expired before it was included in a block")]
+    Expired { hash: CryptoHash },
+    #[error("Failed to decode response: {0}")]
+    Decode(String),
+    #[error("Incompatible response type from RPC {0:?}")]
+    IncompatibleRpcResponseType(QueryResponseKind),
+    #[error("A signer is required to call change methods")]
+    MissingSigner,
+    #[error("Calls attaching a deposit require a full access signer key")]
+    NoFullAccessKey,
+    #[error(transparent)]
+    Signer(#[from] SignerError),
 }
 
+impl ContractInteractionError {
+    /// Whether the call might succeed if it is made again later, e.g. once
+    /// the RPC endpoint recovers or the signer has been topped up.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Query(_)
+            | Self::Transaction(_)
+            | Self::Block(_)
+            | Self::GasPrice(_)
+            | Self::Genesis(_)
+            | Self::Timeout { .. }
+            | Self::Expired { .. }
+            | Self::Signer(_) => true,
+            Self::Execution(_)
+            | Self::Decode(_)
+            | Self::IncompatibleRpcResponseType(_)
+            | Self::MissingSigner
+            | Self::NoFullAccessKey => false,
+        }
+    }
+
+    /// Whether an access key view failed because the key does not exist.
+    pub fn is_unknown_access_key(&self) -> bool {
+        matches!(
+            self,
+            Self::Query(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcQueryError::UnknownAccessKey { .. }
+            )))
+        )
+    }
+
+    /// Whether the transaction failed because the contract panicked with
+    /// `message`.
+    pub fn is_contract_panic(&self, message: &str) -> bool {
+        matches!(self, Self::Execution(e) if e.to_string().contains(message))
+    }
+}
+
+impl From<serde_json::Error> for ContractInteractionError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Decode(e.to_string())
+    }
+}
+
+/// Controls how long and how often [`wait_for_status`] polls for the outcome
+/// of a transaction.
+#[derive(Debug, Clone)]
+pub struct PollConfig {
+    /// Give up waiting after this long.
+    pub deadline: Duration,
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub backoff_multiplier: u32,
+}
+
+impl PollConfig {
+    /// How long to sleep before polling again, given the current interval
+    /// and how long polling has taken so far. `None` once the deadline has
+    /// passed.
+    fn wait(&self, interval: Duration, elapsed: Duration) -> Option<Duration> {
+        (elapsed < self.deadline).then(|| interval.min(self.deadline - elapsed))
+    }
+
+    fn next_interval(&self, interval: Duration) -> Duration {
+        (interval * self.backoff_multiplier).min(self.max_interval)
+    }
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(120),
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(8),
+            backoff_multiplier: 2,
+        }
+    }
+}
+
+/// Returns the current nonce of the signer's access key, whether it has full
+/// access, and the hash of the block it was read at.
 pub async fn valid_for(
-    client: &JsonRpcClient,
-    signer: &InMemorySigner,
-) -> Result<(u64, CryptoHash), Box<dyn std::error::Error>> {
-    let res = client
+    rpc: &RpcPool,
+    signer: &TransactionSigner,
+) -> Result<(u64, bool, CryptoHash), ContractInteractionError> {
+    let res = rpc
         .call(RpcQueryRequest {
             block_reference: BlockReference::latest(),
             request: QueryRequest::ViewAccessKey {
-                account_id: signer.account_id.clone(),
-                public_key: signer.public_key(),
+                account_id: signer.account_id().clone(),
+                public_key: signer.public_key().clone(),
             },
         })
         .await?;
 
     match res.kind {
-        QueryResponseKind::AccessKey(key) => Ok((key.nonce, res.block_hash)),
-        _ => Err("Failed to parse response")?,
+        QueryResponseKind::AccessKey(key) => Ok((
+            key.nonce,
+            key.permission == AccessKeyPermissionView::FullAccess,
+            res.block_hash,
+        )),
+        kind => Err(ContractInteractionError::IncompatibleRpcResponseType(kind)),
     }
 }
 
+async fn block_height(
+    rpc: &RpcPool,
+    block_reference: BlockReference,
+) -> Result<u64, ContractInteractionError> {
+    let block = rpc
+        .call(methods::block::RpcBlockRequest { block_reference })
+        .await?;
+
+    Ok(block.header.height)
+}
+
+async fn transaction_validity_period(
+    rpc: &RpcPool,
+) -> Result<BlockHeightDelta, ContractInteractionError> {
+    let genesis = rpc
+        .call(
+            methods::any::<Result<GenesisConfig, RpcGenesisConfigError>>(
+                "EXPERIMENTAL_genesis_config",
+                serde_json::Value::Null,
+            ),
+        )
+        .await?;
+
+    Ok(genesis.transaction_validity_period)
+}
+
+/// Whether a transaction referencing `reference_height` can no longer be
+/// included in a block at `latest_height`.
+fn has_expired(
+    reference_height: u64,
+    latest_height: u64,
+    validity_period: BlockHeightDelta,
+) -> bool {
+    latest_height > reference_height + validity_period
+}
+
+/// Polls for the final outcome of a transaction with exponential backoff.
+///
+/// The block hash the transaction was signed with is used to detect
+/// transactions that were dropped and can no longer be included on chain.
+/// Until then, giving up at the deadline leaves the outcome unknown.
 pub async fn wait_for_status(
-    client: &JsonRpcClient,
-    account_id: &AccountId,
-    hash: CryptoHash,
-) -> Result<String, Box<dyn std::error::Error>> {
+    rpc: &RpcPool,
+    transaction: &SentTransaction,
+    poll_config: &PollConfig,
+) -> Result<FinalExecutionOutcomeView, ContractInteractionError> {
+    let start = Instant::now();
+    let mut interval = poll_config.initial_interval;
+    // Fetched once the transaction is found to be unknown
+    let mut expiry = None;
+
     loop {
-        let response = client
+        let response = rpc
             .call(methods::tx::RpcTransactionStatusRequest {
                 transaction_info: TransactionInfo::TransactionId {
-                    hash,
-                    account_id: account_id.clone(),
+                    hash: transaction.hash,
+                    account_id: transaction.signer_id.clone(),
                 },
             })
             .await;
 
         match response {
-            Err(err) => match err.handler_error()? {
-                methods::tx::RpcTransactionError::UnknownTransaction { .. } => {
-                    tokio::time::sleep(time::Duration::from_secs(2)).await;
-                    continue;
+            Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::UnknownTransaction { .. },
+            ))) => {
+                let (reference_height, validity_period) = match expiry {
+                    Some(expiry) => expiry,
+                    None => *expiry.insert(futures::try_join!(
+                        block_height(
+                            rpc,
+                            BlockReference::BlockId(BlockId::Hash(
+                                transaction.reference_block_hash
+                            )),
+                        ),
+                        transaction_validity_period(rpc),
+                    )?),
+                };
+                let latest_height = block_height(rpc, BlockReference::latest()).await?;
+
+                if has_expired(reference_height, latest_height, validity_period) {
+                    return Err(ContractInteractionError::Expired {
+                        hash: transaction.hash,
+                    });
                 }
-                err => Err(err)?,
-            },
+            }
+            Err(JsonRpcError::ServerError(JsonRpcServerError::HandlerError(
+                RpcTransactionError::TimeoutError,
+            ))) => {}
+            Err(err) => return Err(err.into()),
             Ok(FinalExecutionOutcomeView {
                 status: FinalExecutionStatus::Failure(e),
                 ..
             }) => {
-                return Err(Box::new(TransactionStatusError::ExecutionError(e)));
+                return Err(ContractInteractionError::Execution(e));
             }
-            Ok(FinalExecutionOutcomeView {
-                status: FinalExecutionStatus::SuccessValue(s),
-                ..
-            }) => {
-                return Ok(s);
+            Ok(
+                outcome @ FinalExecutionOutcomeView {
+                    status: FinalExecutionStatus::SuccessValue(_),
+                    ..
+                },
+            ) => {
+                return Ok(outcome);
             }
             _ => {
                 // Transaction is currently executing
-                tokio::time::sleep(time::Duration::from_secs(2)).await;
-                continue;
             }
         }
-    }
-}
 
-#[derive(Debug, Error)]
-pub enum ContractInteractionError {
-    #[error("Incompatible response type from RPC {0:?}")]
-    IncompatibleRpcResponseType(QueryResponseKind),
+        let elapsed = start.elapsed();
+        let wait = poll_config.wait(interval, elapsed).ok_or_else(|| {
+            ContractInteractionError::Timeout {
+                transaction: transaction.clone(),
+                elapsed,
+            }
+        })?;
+
+        tokio::time::sleep(wait).await;
+        interval = poll_config.next_interval(interval);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use near_crypto::{KeyType, SecretKey};
+    use std::time::Duration;
+
+    use super::{has_expired, PollConfig};
+
+    #[test]
+    fn backs_off_up_to_max_interval() {
+        let config = PollConfig::default();
+
+        let mut interval = config.initial_interval;
+        let mut intervals = vec![];
+        for _ in 0..7 {
+            intervals.push(interval.as_millis());
+            interval = config.next_interval(interval);
+        }
+
+        assert_eq!(intervals, [500, 1000, 2000, 4000, 8000, 8000, 8000]);
+    }
 
     #[test]
-    fn generate_ed25519_key() {
-        let k = SecretKey::from_random(KeyType::ED25519);
-        println!("Secret key: {}", k.to_string());
-        println!("Verify key: {}", k.public_key().to_string());
+    fn stops_waiting_at_deadline() {
+        let config = PollConfig {
+            deadline: Duration::from_secs(10),
+            ..PollConfig::default()
+        };
+        let interval = Duration::from_secs(8);
+
+        assert_eq!(config.wait(interval, Duration::ZERO), Some(interval));
+        // Never sleeps past the deadline
+        assert_eq!(
+            config.wait(interval, Duration::from_secs(7)),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(config.wait(interval, Duration::from_secs(10)), None);
+        assert_eq!(config.wait(interval, Duration::from_secs(11)), None);
+    }
+
+    #[test]
+    fn expires_after_validity_period() {
+        // `transaction_validity_period` of mainnet and testnet
+        let period = 86_400;
+
+        assert!(!has_expired(100, 100, period));
+        assert!(!has_expired(100, 100 + period, period));
+        assert!(has_expired(100, 101 + period, period));
     }
 }
@@ -0,0 +1,277 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use near_crypto::PublicKey;
+use near_primitives::{hash::CryptoHash, types::Balance};
+use tokio::sync::Mutex;
+
+use crate::signer::TransactionSigner;
+
+use super::{rpc::RpcPool, valid_for, ContractInteractionError};
+
+/// How long a cached block hash is used as a transaction's reference block
+/// before it is refreshed from the chain.
+const BLOCK_HASH_TTL: Duration = Duration::from_secs(60);
+
+/// Whether an access key can sign a function call attaching `deposit`.
+/// Function call keys can't attach deposits.
+pub fn can_sign(full_access: bool, deposit: Balance) -> bool {
+    full_access || deposit == 0
+}
+
+struct KeyState {
+    nonce: u64,
+    full_access: bool,
+    block_hash: CryptoHash,
+    fetched_at: Instant,
+}
+
+struct ManagedKey {
+    signer: TransactionSigner,
+    state: Mutex<Option<KeyState>>,
+}
+
+/// Everything required to build a transaction that will not collide with
+/// other transactions issued by the same [`NonceManager`].
+pub struct Lease {
+    pub signer: TransactionSigner,
+    pub nonce: u64,
+    pub block_hash: CryptoHash,
+}
+
+/// Caches and hands out access key nonces so that concurrent transactions
+/// from the same account never reuse a nonce.
+///
+/// Transactions are spread round-robin across a pool of access keys (all
+/// belonging to the same account), so that keys can be used in parallel.
+/// Calls that attach a deposit skip function call keys.
+pub struct NonceManager {
+    rpc: RpcPool,
+    keys: Vec<ManagedKey>,
+    next_key: AtomicUsize,
+}
+
+impl NonceManager {
+    pub fn new(rpc: RpcPool, signers: Vec<TransactionSigner>) -> Self {
+        assert!(
+            !signers.is_empty(),
+            "NonceManager requires at least one signer"
+        );
+
+        Self {
+            rpc,
+            keys: signers
+                .into_iter()
+                .map(|signer| ManagedKey {
+                    signer,
+                    state: Mutex::new(None),
+                })
+                .collect(),
+            next_key: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn signers(&self) -> impl Iterator<Item = &TransactionSigner> {
+        self.keys.iter().map(|k| &k.signer)
+    }
+
+    /// Reserves the next nonce for one of the keys in the pool that can sign
+    /// a call attaching `deposit`.
+    pub async fn next(&self, deposit: Balance) -> Result<Lease, ContractInteractionError> {
+        let start = self.next_key.fetch_add(1, Ordering::Relaxed);
+
+        for offset in 0..self.keys.len() {
+            let key = &self.keys[(start + offset) % self.keys.len()];
+            if let Some(lease) = self.lease(key, deposit).await? {
+                return Ok(lease);
+            }
+        }
+
+        Err(ContractInteractionError::NoFullAccessKey)
+    }
+
+    /// Reserves the next nonce for `key`, unless it can't sign a call
+    /// attaching `deposit`. Its permission is only known once it is synced.
+    async fn lease(
+        &self,
+        key: &ManagedKey,
+        deposit: Balance,
+    ) -> Result<Option<Lease>, ContractInteractionError> {
+        let mut state = key.state.lock().await;
+
+        let stale = state
+            .as_ref()
+            .is_none_or(|s| s.fetched_at.elapsed() > BLOCK_HASH_TTL);
+
+        if stale {
+            let (chain_nonce, full_access, block_hash) = valid_for(&self.rpc, &key.signer).await?;
+            // Never go backwards: transactions using cached nonces may still
+            // be in flight and not yet reflected on chain.
+            let nonce = state
+                .as_ref()
+                .map_or(chain_nonce, |s| s.nonce.max(chain_nonce));
+            *state = Some(KeyState {
+                nonce,
+                full_access,
+                block_hash,
+                fetched_at: Instant::now(),
+            });
+        }
+
+        let state = state.as_mut().unwrap();
+        if !can_sign(state.full_access, deposit) {
+            return Ok(None);
+        }
+        state.nonce += 1;
+
+        Ok(Some(Lease {
+            signer: key.signer.clone(),
+            nonce: state.nonce,
+            block_hash: state.block_hash,
+        }))
+    }
+
+    /// Discards cached state for a key after the chain rejected a nonce. If
+    /// the node reported the access key's current nonce, the counter is moved
+    /// up to it (never down, since leased nonces may still be in flight);
+    /// otherwise the nonce is fetched again on next use.
+    pub async fn resync(&self, public_key: &PublicKey, ak_nonce: Option<u64>) {
+        let key = match self
+            .keys
+            .iter()
+            .find(|k| k.signer.public_key() == public_key)
+        {
+            Some(key) => key,
+            None => return,
+        };
+
+        let mut state = key.state.lock().await;
+
+        match (ak_nonce, state.as_mut()) {
+            (Some(ak_nonce), Some(state)) => state.nonce = state.nonce.max(ak_nonce),
+            _ => *state = None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_primitives::hash::CryptoHash;
+
+    use super::{ContractInteractionError, KeyState, NonceManager, RpcPool};
+
+    fn seeded_manager(keys: usize) -> NonceManager {
+        seeded_manager_with_access(&vec![true; keys])
+    }
+
+    /// One key per entry of `full_access`, seeded with its permission.
+    fn seeded_manager_with_access(full_access: &[bool]) -> NonceManager {
+        let signers = (0..full_access.len())
+            .map(|i| {
+                InMemorySigner::from_seed(
+                    "registry.testnet".parse().unwrap(),
+                    KeyType::ED25519,
+                    &format!("seed{i}"),
+                )
+                .into()
+            })
+            .collect();
+
+        // Never contacted, since every key is seeded with fresh state
+        let manager = NonceManager::new(RpcPool::from_urls(["http://127.0.0.1:1"], None), signers);
+
+        for (key, &full_access) in manager.keys.iter().zip(full_access) {
+            *key.state.try_lock().unwrap() = Some(KeyState {
+                nonce: 100,
+                full_access,
+                block_hash: CryptoHash::default(),
+                fetched_at: Instant::now(),
+            });
+        }
+
+        manager
+    }
+
+    #[tokio::test]
+    async fn concurrent_nonces_are_unique() {
+        let manager = Arc::new(seeded_manager(1));
+
+        let handles = (0..32)
+            .map(|_| {
+                let manager = Arc::clone(&manager);
+                tokio::spawn(async move { manager.next(0).await.unwrap().nonce })
+            })
+            .collect::<Vec<_>>();
+
+        let mut nonces = Vec::new();
+        for handle in handles {
+            nonces.push(handle.await.unwrap());
+        }
+        nonces.sort_unstable();
+
+        assert_eq!(nonces, (101..=132).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn round_robin_across_pool() {
+        let manager = seeded_manager(2);
+
+        let a = manager.next(0).await.unwrap();
+        let b = manager.next(0).await.unwrap();
+        let c = manager.next(0).await.unwrap();
+
+        assert_ne!(a.signer.public_key(), b.signer.public_key());
+        assert_eq!(a.signer.public_key(), c.signer.public_key());
+        assert_eq!((a.nonce, b.nonce, c.nonce), (101, 101, 102));
+    }
+
+    #[tokio::test]
+    async fn resync_to_reported_nonce() {
+        let manager = seeded_manager(1);
+        let public_key = manager.keys[0].signer.public_key().clone();
+
+        manager.next(0).await.unwrap();
+        manager.resync(&public_key, Some(200)).await;
+
+        assert_eq!(manager.next(0).await.unwrap().nonce, 201);
+    }
+
+    #[tokio::test]
+    async fn resync_keeps_leased_nonces() {
+        let manager = seeded_manager(1);
+        let public_key = manager.keys[0].signer.public_key().clone();
+
+        // Leased 101 and 102, but the chain has only seen 101 so far
+        manager.next(0).await.unwrap();
+        let in_flight = manager.next(0).await.unwrap().nonce;
+        manager.resync(&public_key, Some(101)).await;
+
+        assert!(manager.next(0).await.unwrap().nonce > in_flight);
+    }
+
+    #[tokio::test]
+    async fn deposits_only_use_full_access_keys() {
+        let manager = seeded_manager_with_access(&[false, true]);
+        let full_access = manager.keys[1].signer.public_key().clone();
+
+        for _ in 0..3 {
+            let lease = manager.next(1).await.unwrap();
+            assert_eq!(lease.signer.public_key(), &full_access);
+        }
+        // Calls without a deposit still use every key
+        let a = manager.next(0).await.unwrap();
+        let b = manager.next(0).await.unwrap();
+        assert_ne!(a.signer.public_key(), b.signer.public_key());
+
+        let manager = seeded_manager_with_access(&[false]);
+        assert!(matches!(
+            manager.next(1).await,
+            Err(ContractInteractionError::NoFullAccessKey)
+        ));
+    }
+}
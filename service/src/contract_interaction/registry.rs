@@ -0,0 +1,359 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use model::{
+    code_hash::CodeHash,
+    verification::{BuildLog, Verification, VerificationFailure, VerificationRequest},
+};
+use near_crypto::PublicKey;
+use near_primitives::{
+    hash::CryptoHash,
+    serialize::from_base64,
+    types::{AccountId, Balance, BlockReference, Finality, Gas},
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::{
+    call_profiles::{CallProfile, CallProfiles},
+    network_config::NetworkConfig,
+    signer::TransactionSigner,
+};
+
+use super::{
+    change::{change, ChangeOutcome},
+    nonce::NonceManager,
+    rpc::RpcPool,
+    view::{code_hash, view},
+    wait_for_status, ContractInteractionError, PollConfig, SentTransaction,
+};
+
+/// Running totals of the costs of calls to a single contract method.
+#[derive(Debug, Clone, Default)]
+pub struct CallCosts {
+    pub calls: u64,
+    /// Calls that did not complete successfully (not included in `calls`)
+    pub failures: u64,
+    pub gas_burnt: u128,
+    pub tokens_burnt: Balance,
+    /// Gas burnt by the most recent call
+    pub last_gas_burnt: Gas,
+}
+
+/// Typed binding to the contract registry's methods.
+#[derive(Clone)]
+pub struct RegistryClient {
+    network_config: NetworkConfig,
+    rpc: RpcPool,
+    contract_id: AccountId,
+    nonces: Option<Arc<NonceManager>>,
+    poll_config: PollConfig,
+    call_profiles: CallProfiles,
+    call_costs: Arc<Mutex<HashMap<String, CallCosts>>>,
+}
+
+impl RegistryClient {
+    /// Creates a read-only client. Change methods will fail until a signer is
+    /// provided with [`RegistryClient::with_signers`].
+    pub fn new(network_config: NetworkConfig, contract_id: AccountId) -> Self {
+        let rpc = RpcPool::new(&network_config);
+
+        Self {
+            network_config,
+            rpc,
+            contract_id,
+            nonces: None,
+            poll_config: PollConfig::default(),
+            call_profiles: CallProfiles::default(),
+            call_costs: Default::default(),
+        }
+    }
+
+    /// Signs change calls with a pool of access keys belonging to the same
+    /// account. Keys are used round-robin, so transactions can be sent in
+    /// parallel. Calls attaching a deposit only use full access keys.
+    pub fn with_signers(self, signers: Vec<TransactionSigner>) -> Self {
+        let nonces = NonceManager::new(self.rpc.clone(), signers);
+
+        Self {
+            nonces: Some(Arc::new(nonces)),
+            ..self
+        }
+    }
+
+    /// Configures how long change calls wait for their transaction outcome.
+    pub fn with_poll_config(self, poll_config: PollConfig) -> Self {
+        Self {
+            poll_config,
+            ..self
+        }
+    }
+
+    /// Configures gas and deposit per contract method.
+    pub fn with_call_profiles(self, call_profiles: CallProfiles) -> Self {
+        Self {
+            call_profiles,
+            ..self
+        }
+    }
+
+    /// Costs of all change calls made through this client (and its clones)
+    /// so far, by method name.
+    pub fn call_costs(&self) -> HashMap<String, CallCosts> {
+        self.call_costs.lock().unwrap().clone()
+    }
+
+    fn record_failure(&self, method_name: &str) {
+        let mut call_costs = self.call_costs.lock().unwrap();
+        call_costs
+            .entry(method_name.to_string())
+            .or_default()
+            .failures += 1;
+    }
+
+    fn record_costs(&self, method_name: &str, outcome: &ChangeOutcome) {
+        let mut call_costs = self.call_costs.lock().unwrap();
+        let costs = call_costs.entry(method_name.to_string()).or_default();
+        costs.calls += 1;
+        costs.gas_burnt += u128::from(outcome.gas_burnt);
+        costs.tokens_burnt += outcome.tokens_burnt;
+        costs.last_gas_burnt = outcome.gas_burnt;
+    }
+
+    /// Gas and deposit attached to calls of `method_name`.
+    pub fn call_profile(&self, method_name: &str) -> CallProfile {
+        self.call_profiles.get(method_name)
+    }
+
+    /// Public keys that change calls are signed with.
+    pub fn signer_keys(&self) -> Vec<PublicKey> {
+        self.nonces.as_ref().map_or_else(Vec::new, |nonces| {
+            nonces.signers().map(|s| s.public_key().clone()).collect()
+        })
+    }
+
+    pub fn contract_id(&self) -> &AccountId {
+        &self.contract_id
+    }
+
+    pub fn rpc(&self) -> &RpcPool {
+        &self.rpc
+    }
+
+    pub fn network_config(&self) -> &NetworkConfig {
+        &self.network_config
+    }
+
+    async fn view<T: DeserializeOwned>(
+        &self,
+        method_name: &str,
+        args: serde_json::Value,
+    ) -> Result<T, ContractInteractionError> {
+        let value = view(
+            &self.rpc,
+            self.contract_id.clone(),
+            method_name.to_string(),
+            &args,
+        )
+        .await?;
+
+        Ok(serde_json::from_value(value)?)
+    }
+
+    async fn change_outcome(
+        &self,
+        method_name: &str,
+        args: serde_json::Value,
+        extra_deposit: Balance,
+    ) -> Result<ChangeOutcome, ContractInteractionError> {
+        let nonces = self
+            .nonces
+            .as_ref()
+            .ok_or(ContractInteractionError::MissingSigner)?;
+
+        let profile = self.call_profiles.get(method_name);
+
+        let outcome = change(
+            &self.rpc,
+            nonces,
+            &self.contract_id,
+            method_name,
+            args,
+            CallProfile {
+                deposit: profile.deposit + extra_deposit,
+                ..profile
+            },
+            &self.poll_config,
+        )
+        .await
+        .inspect_err(|_| self.record_failure(method_name))?;
+
+        self.record_costs(method_name, &outcome);
+
+        Ok(outcome)
+    }
+
+    async fn change<T: DeserializeOwned>(
+        &self,
+        method_name: &str,
+        args: serde_json::Value,
+        extra_deposit: Balance,
+    ) -> Result<T, ContractInteractionError> {
+        let outcome = self
+            .change_outcome(method_name, args, extra_deposit)
+            .await?;
+
+        let bytes = from_base64(&outcome.value)
+            .map_err(|e| ContractInteractionError::Decode(e.to_string()))?;
+
+        // Methods without a return value produce an empty result
+        if bytes.is_empty() {
+            Ok(serde_json::from_value(serde_json::Value::Null)?)
+        } else {
+            Ok(serde_json::from_slice(&bytes)?)
+        }
+    }
+
+    pub async fn get_verification_fee(&self) -> Result<u128, ContractInteractionError> {
+        let fee: String = self.view("get_verification_fee", json!({})).await?;
+        fee.parse()
+            .map_err(|e| ContractInteractionError::Decode(format!("Invalid fee {fee:?}: {e}")))
+    }
+
+    pub async fn get_verification_request(
+        &self,
+        id: u64,
+    ) -> Result<Option<VerificationRequest>, ContractInteractionError> {
+        self.view("get_verification_request", json!({ "id": id.to_string() }))
+            .await
+    }
+
+    pub async fn get_verification_result(
+        &self,
+        request_id: u64,
+    ) -> Result<Option<Verification>, ContractInteractionError> {
+        self.view(
+            "get_verification_result",
+            json!({ "request_id": request_id.to_string() }),
+        )
+        .await
+    }
+
+    pub async fn get_verification_failure(
+        &self,
+        request_id: u64,
+    ) -> Result<Option<VerificationFailure>, ContractInteractionError> {
+        self.view(
+            "get_verification_failure",
+            json!({ "request_id": request_id.to_string() }),
+        )
+        .await
+    }
+
+    pub async fn verify_code_hash(
+        &self,
+        code_hash: &CodeHash,
+    ) -> Result<Option<Verification>, ContractInteractionError> {
+        self.view("verify_code_hash", json!({ "code_hash": code_hash }))
+            .await
+    }
+
+    /// Looks up the verification of the contract currently deployed to
+    /// `account_id`. Returns `None` if the account has no contract.
+    pub async fn verify_account(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<(CodeHash, Option<Verification>)>, ContractInteractionError> {
+        self.verify_account_at(account_id, BlockReference::Finality(Finality::Final))
+            .await
+    }
+
+    /// Like [`RegistryClient::verify_account`], but for the contract deployed
+    /// as of `block_reference`, e.g. a past block height.
+    pub async fn verify_account_at(
+        &self,
+        account_id: &AccountId,
+        block_reference: BlockReference,
+    ) -> Result<Option<(CodeHash, Option<Verification>)>, ContractInteractionError> {
+        let code_hash = match code_hash(&self.rpc, account_id.clone(), block_reference).await? {
+            Some(code_hash) => code_hash,
+            None => return Ok(None),
+        };
+        let verification = self.verify_code_hash(&code_hash).await?;
+
+        Ok(Some((code_hash, verification)))
+    }
+
+    pub async fn get_pending_requests(
+        &self,
+    ) -> Result<Vec<VerificationRequest>, ContractInteractionError> {
+        self.view("get_pending_requests", json!({})).await
+    }
+
+    pub async fn get_owner(&self) -> Result<Option<AccountId>, ContractInteractionError> {
+        self.view("own_get_owner", json!({})).await
+    }
+
+    /// Submits a new verification request, attaching `fee` plus the deposit
+    /// configured for `request_verification` to cover storage.
+    pub async fn request_verification(
+        &self,
+        repository: &str,
+        checkout: &str,
+        path: &str,
+        fee: u128,
+    ) -> Result<VerificationRequest, ContractInteractionError> {
+        self.change(
+            "request_verification",
+            json!({
+                "repository": repository,
+                "checkout": checkout,
+                "path": path,
+                "fee": fee.to_string(),
+            }),
+            fee,
+        )
+        .await
+    }
+
+    /// Waits again for the outcome of a change call that timed out. Returns
+    /// the hash of the transaction.
+    pub async fn transaction_outcome(
+        &self,
+        transaction: &SentTransaction,
+    ) -> Result<CryptoHash, ContractInteractionError> {
+        let outcome: ChangeOutcome = wait_for_status(&self.rpc, transaction, &self.poll_config)
+            .await?
+            .into();
+        Ok(outcome.transaction_hash)
+    }
+
+    /// Returns the hash of the resolution transaction.
+    pub async fn verification_success(
+        &self,
+        result: &Verification,
+    ) -> Result<CryptoHash, ContractInteractionError> {
+        let outcome = self
+            .change_outcome("verification_success", json!({ "result": result }), 0)
+            .await?;
+        Ok(outcome.transaction_hash)
+    }
+
+    /// Returns the hash of the resolution transaction.
+    pub async fn verification_failure(
+        &self,
+        id: u64,
+        log: Option<&BuildLog>,
+    ) -> Result<CryptoHash, ContractInteractionError> {
+        let outcome = self
+            .change_outcome(
+                "verification_failure",
+                json!({ "id": id.to_string(), "log": log }),
+                0,
+            )
+            .await?;
+        Ok(outcome.transaction_hash)
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use near_jsonrpc_client::{
+    errors::{JsonRpcError, JsonRpcServerError, JsonRpcServerResponseStatusError},
+    methods::{self, RpcMethod},
+    JsonRpcClient, MethodCallResult,
+};
+
+use tracing::warn;
+
+use crate::network_config::NetworkConfig;
+
+/// Number of passes over the whole endpoint list before giving up.
+const MAX_ROUNDS: usize = 3;
+/// Pause between passes over the endpoint list.
+const ROUND_DELAY: Duration = Duration::from_millis(500);
+
+struct Endpoint {
+    client: JsonRpcClient,
+    healthy: AtomicBool,
+}
+
+struct Inner {
+    endpoints: Vec<Endpoint>,
+    archival: Option<JsonRpcClient>,
+    preferred: AtomicUsize,
+}
+
+/// A set of reusable RPC clients that fails over between endpoints on
+/// transport errors.
+#[derive(Clone)]
+pub struct RpcPool {
+    inner: Arc<Inner>,
+}
+
+/// Describes errors that indicate a problem with the endpoint (rather than
+/// with the request), so that another endpoint may succeed.
+fn endpoint_failure<E>(err: &JsonRpcError<E>) -> Option<String> {
+    match err {
+        JsonRpcError::TransportError(e) => Some(e.to_string()),
+        JsonRpcError::ServerError(JsonRpcServerError::InternalError { info }) => {
+            Some(format!("internal error: {info:?}"))
+        }
+        JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(status)) => {
+            match status {
+                JsonRpcServerResponseStatusError::TooManyRequests => Some(status.to_string()),
+                JsonRpcServerResponseStatusError::Unexpected { status: code }
+                    if code.is_server_error() =>
+                {
+                    Some(status.to_string())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+impl RpcPool {
+    pub fn new(network_config: &NetworkConfig) -> Self {
+        Self::from_urls(
+            network_config.rpc_urls(),
+            Some(network_config.archival_url.as_str()).filter(|u| !u.is_empty()),
+        )
+    }
+
+    pub fn from_urls<'a>(
+        urls: impl IntoIterator<Item = &'a str>,
+        archival_url: Option<&str>,
+    ) -> Self {
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                client: JsonRpcClient::connect(url),
+                healthy: AtomicBool::new(true),
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!endpoints.is_empty(), "At least one RPC URL is required");
+
+        Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                archival: archival_url.map(JsonRpcClient::connect),
+                preferred: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// URLs of all endpoints with their most recently observed health.
+    pub fn endpoint_health(&self) -> Vec<(String, bool)> {
+        self.inner
+            .endpoints
+            .iter()
+            .map(|e| {
+                (
+                    e.client.server_addr().to_string(),
+                    e.healthy.load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+
+    /// Sends the request to the preferred endpoint, failing over to the
+    /// others (healthy ones first) if the endpoint appears to be down.
+    pub async fn call<M: RpcMethod>(&self, method: M) -> MethodCallResult<M::Response, M::Error> {
+        let endpoints = &self.inner.endpoints;
+        let mut round = 0;
+
+        loop {
+            let start = self.inner.preferred.load(Ordering::Relaxed);
+            let order = (0..endpoints.len())
+                .map(|i| (start + i) % endpoints.len())
+                .collect::<Vec<_>>();
+            // Prefer endpoints that were healthy at last check
+            let (healthy, unhealthy): (Vec<usize>, Vec<usize>) = order
+                .into_iter()
+                .partition(|&i| endpoints[i].healthy.load(Ordering::Relaxed));
+
+            let mut last_err = None;
+
+            for i in healthy.into_iter().chain(unhealthy) {
+                let endpoint = &endpoints[i];
+                let result = endpoint.client.call(&method).await;
+                match result.as_ref().err().and_then(endpoint_failure) {
+                    Some(reason) => {
+                        warn!(
+                            endpoint = endpoint.client.server_addr(),
+                            "RPC endpoint failed: {reason}"
+                        );
+                        endpoint.healthy.store(false, Ordering::Relaxed);
+                        last_err = result.err();
+                    }
+                    None => {
+                        endpoint.healthy.store(true, Ordering::Relaxed);
+                        self.inner.preferred.store(i, Ordering::Relaxed);
+                        return result;
+                    }
+                }
+            }
+
+            round += 1;
+            if round >= MAX_ROUNDS {
+                return Err(last_err.unwrap());
+            }
+
+            tokio::time::sleep(ROUND_DELAY).await;
+        }
+    }
+
+    /// Sends the request to the archival endpoint, for reads of historical
+    /// state. Falls back to the regular endpoints if no archival endpoint is
+    /// configured.
+    pub async fn call_archival<M: RpcMethod>(
+        &self,
+        method: M,
+    ) -> MethodCallResult<M::Response, M::Error> {
+        match &self.inner.archival {
+            Some(archival) => archival.call(method).await,
+            None => self.call(method).await,
+        }
+    }
+
+    /// Queries the health of every endpoint and records the results.
+    pub async fn check_health(&self) {
+        for endpoint in &self.inner.endpoints {
+            let healthy = endpoint
+                .client
+                .call(methods::health::RpcHealthRequest)
+                .await
+                .is_ok();
+            endpoint.healthy.store(healthy, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_jsonrpc_client::methods;
+
+    use super::RpcPool;
+
+    #[tokio::test]
+    async fn reports_failure_of_all_endpoints() {
+        // Nothing listens on these ports
+        let pool = RpcPool::from_urls(["http://127.0.0.1:1", "http://127.0.0.1:2"], None);
+
+        assert!(pool.call(methods::status::RpcStatusRequest).await.is_err());
+        assert!(pool.endpoint_health().iter().all(|(_, healthy)| !healthy));
+    }
+}
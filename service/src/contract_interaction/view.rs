@@ -1,38 +1,142 @@
-use near_jsonrpc_client::{methods, JsonRpcClient};
-use near_jsonrpc_primitives::types::query::QueryResponseKind;
-use near_primitives::types::{AccountId, BlockReference, Finality, FunctionArgs};
-use near_primitives::views::QueryRequest;
+use model::code_hash::CodeHash;
+use near_crypto::PublicKey;
+use near_jsonrpc_client::methods;
+use near_jsonrpc_primitives::types::query::{QueryResponseKind, RpcQueryResponse};
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{AccountId, Balance, BlockReference, Finality, FunctionArgs};
+use near_primitives::views::{AccessKeyView, AccountView, QueryRequest};
 
 use serde_json::from_slice;
 
-use crate::network_config::NetworkConfig;
+use super::{rpc::RpcPool, ContractInteractionError};
 
-use super::ContractInteractionError;
+/// Reads state as of `block_reference`. Reads of specific historical blocks
+/// go to the archival endpoint.
+async fn query(
+    rpc: &RpcPool,
+    block_reference: BlockReference,
+    request: QueryRequest,
+) -> Result<RpcQueryResponse, ContractInteractionError> {
+    let historical = matches!(block_reference, BlockReference::BlockId(_));
+    let request = methods::query::RpcQueryRequest {
+        block_reference,
+        request,
+    };
+
+    Ok(if historical {
+        rpc.call_archival(request).await?
+    } else {
+        rpc.call(request).await?
+    })
+}
 
 pub async fn view(
-    network_config: &NetworkConfig,
+    rpc: &RpcPool,
     contract_id: AccountId,
     method_name: String,
     args: &serde_json::Value,
-) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-    let client = JsonRpcClient::connect(&network_config.node_url);
-
-    let request = methods::query::RpcQueryRequest {
-        block_reference: BlockReference::Finality(Finality::Final),
-        request: QueryRequest::CallFunction {
+) -> Result<serde_json::Value, ContractInteractionError> {
+    let response = query(
+        rpc,
+        BlockReference::Finality(Finality::Final),
+        QueryRequest::CallFunction {
             account_id: contract_id,
             method_name,
             args: FunctionArgs::from(args.to_string().into_bytes()),
         },
-    };
-
-    let response = client.call(request).await?;
+    )
+    .await?;
 
     if let QueryResponseKind::CallResult(result) = response.kind {
-        from_slice(&result.result[..]).map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
+        Ok(from_slice(&result.result[..])?)
     } else {
-        Err(Box::new(
-            ContractInteractionError::IncompatibleRpcResponseType(response.kind),
+        Err(ContractInteractionError::IncompatibleRpcResponseType(
+            response.kind,
         ))
     }
 }
+
+pub async fn account(
+    rpc: &RpcPool,
+    account_id: AccountId,
+) -> Result<AccountView, ContractInteractionError> {
+    account_at(rpc, account_id, BlockReference::Finality(Finality::Final)).await
+}
+
+pub async fn account_at(
+    rpc: &RpcPool,
+    account_id: AccountId,
+    block_reference: BlockReference,
+) -> Result<AccountView, ContractInteractionError> {
+    let response = query(
+        rpc,
+        block_reference,
+        QueryRequest::ViewAccount { account_id },
+    )
+    .await?;
+
+    match response.kind {
+        QueryResponseKind::ViewAccount(account) => Ok(account),
+        kind => Err(ContractInteractionError::IncompatibleRpcResponseType(kind)),
+    }
+}
+
+pub async fn access_key(
+    rpc: &RpcPool,
+    account_id: AccountId,
+    public_key: PublicKey,
+) -> Result<AccessKeyView, ContractInteractionError> {
+    let response = rpc
+        .call(methods::query::RpcQueryRequest {
+            block_reference: BlockReference::Finality(Finality::Final),
+            request: QueryRequest::ViewAccessKey {
+                account_id,
+                public_key,
+            },
+        })
+        .await?;
+
+    match response.kind {
+        QueryResponseKind::AccessKey(access_key) => Ok(access_key),
+        kind => Err(ContractInteractionError::IncompatibleRpcResponseType(kind)),
+    }
+}
+
+/// Current gas price, in yoctoNEAR per unit of gas.
+pub async fn gas_price(rpc: &RpcPool) -> Result<Balance, ContractInteractionError> {
+    let response = rpc
+        .call(methods::gas_price::RpcGasPriceRequest { block_id: None })
+        .await?;
+
+    Ok(response.gas_price)
+}
+
+/// Hash of the contract deployed to an account as of `block_reference`, or
+/// `None` if the account had no contract. NEAR code hashes are SHA-256 hashes
+/// of the wasm, so they are directly comparable to [`CodeHash::hash_bytes`].
+pub async fn code_hash(
+    rpc: &RpcPool,
+    account_id: AccountId,
+    block_reference: BlockReference,
+) -> Result<Option<CodeHash>, ContractInteractionError> {
+    let account = account_at(rpc, account_id, block_reference).await?;
+
+    if account.code_hash == CryptoHash::default() {
+        Ok(None)
+    } else {
+        Ok(Some(CodeHash::from(&account.code_hash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use model::code_hash::CodeHash;
+    use near_primitives::hash::hash;
+
+    #[test]
+    fn code_hash_matches_account_code_hash() {
+        let wasm = b"\0asm\x01\0\0\0";
+
+        assert_eq!(CodeHash::from(&hash(wasm)), CodeHash::hash_bytes(wasm));
+    }
+}
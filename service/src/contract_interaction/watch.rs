@@ -4,13 +4,12 @@ use near_primitives::types::AccountId;
 use serde::de::DeserializeOwned;
 use tokio::sync::mpsc::{self, Receiver};
 use tokio::time;
+use tracing::{debug, warn};
 
-use crate::network_config::NetworkConfig;
-
-use super::view::view;
+use super::{rpc::RpcPool, view::view};
 
 pub fn list<T, U>(
-    network_config: NetworkConfig,
+    rpc: RpcPool,
     contract_id: AccountId,
     method_name: String,
     args: serde_json::Value,
@@ -31,35 +30,30 @@ where
         loop {
             interval.tick().await;
             let mut largest_in_round: Option<U> = None;
-            let items = view(
-                &network_config,
-                contract_id.clone(),
-                method_name.clone(),
-                &args,
-            )
-            .await
-            .ok()
-            .as_ref()
-            .and_then(|view| view.as_array())
-            .map(|arr| {
-                arr.into_iter()
-                    .map(|item| serde_json::from_value::<T>(item.clone()))
-                    .filter_map(|item| match item {
-                        Err(ref e) => {
-                            // May be intentional (e.g. filter by parse-ability)
-                            println!("Error parsing item: {}", e);
-                            None
-                        }
-                        Ok(i) => Some(i),
-                    })
-                    .filter(|item| {
-                        // Only take items that are "larger" than those we've seen already
-                        largest_overall
-                            .as_ref()
-                            .map_or(true, |largest_overall| &item.seq_id() > largest_overall)
-                    })
-                    .collect::<Vec<T>>()
-            });
+            let items = view(&rpc, contract_id.clone(), method_name.clone(), &args)
+                .await
+                .ok()
+                .as_ref()
+                .and_then(|view| view.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .map(|item| serde_json::from_value::<T>(item.clone()))
+                        .filter_map(|item| match item {
+                            Err(ref e) => {
+                                // May be intentional (e.g. filter by parse-ability)
+                                debug!("Error parsing item: {e}");
+                                None
+                            }
+                            Ok(i) => Some(i),
+                        })
+                        .filter(|item| {
+                            // Only take items that are "larger" than those we've seen already
+                            largest_overall
+                                .as_ref()
+                                .is_none_or(|largest_overall| &item.seq_id() > largest_overall)
+                        })
+                        .collect::<Vec<T>>()
+                });
 
             if let Some(items) = items {
                 for item in items {
@@ -74,7 +68,7 @@ where
 
                     match tx.send(item).await {
                         Ok(()) => {}
-                        Err(e) => println!("Error sending across channel: {}", e),
+                        Err(e) => warn!("Error sending across channel: {e}"),
                     }
                 }
             }
@@ -1,20 +1,74 @@
-#[allow(dead_code)]
-pub const PORT: &'static str = "PORT";
-#[allow(dead_code)]
-pub const CIRCLECI_WEBHOOK_SECRET: &'static str = "CIRCLECI_WEBHOOK_SECRET";
-#[allow(dead_code)]
-pub const CIRCLECI_PROJECT_SLUG: &'static str = "CIRCLECI_PROJECT_SLUG";
-#[allow(dead_code)]
-pub const CIRCLECI_API_KEY: &'static str = "CIRCLECI_API_KEY";
-#[allow(dead_code)]
-pub const CIRCLECI_JOB_NAME: &'static str = "CIRCLECI_JOB_NAME";
-#[allow(dead_code)]
-pub const NETWORK_CONFIG: &'static str = "NETWORK_CONFIG";
-#[allow(dead_code)]
-pub const CONTRACT_ID: &'static str = "CONTRACT_ID";
-#[allow(dead_code)]
-pub const ACCOUNT_ID: &'static str = "ACCOUNT_ID";
-#[allow(dead_code)]
-pub const SECRET_KEY: &'static str = "SECRET_KEY";
-#[allow(dead_code)]
-pub const REPOSITORY_PATH: &'static str = "SECRET_KEY";
+//! Environment variables that override values from the configuration file.
+
+/// Path to the TOML configuration file.
+pub const CONFIG: &str = "CONFIG";
+pub const BIND_ADDRESS: &str = "BIND_ADDRESS";
+/// Overrides only the port of the bind address.
+pub const PORT: &str = "PORT";
+/// PEM certificate chain; serves HTTPS when set along with [`TLS_KEY_PATH`].
+pub const TLS_CERT_PATH: &str = "TLS_CERT_PATH";
+/// PEM private key for [`TLS_CERT_PATH`].
+pub const TLS_KEY_PATH: &str = "TLS_KEY_PATH";
+/// Comma-separated list of active secrets, to allow rotation.
+pub const CIRCLECI_WEBHOOK_SECRET: &str = "CIRCLECI_WEBHOOK_SECRET";
+pub const CIRCLECI_PROJECT_SLUG: &str = "CIRCLECI_PROJECT_SLUG";
+pub const CIRCLECI_API_KEY: &str = "CIRCLECI_API_KEY";
+pub const CIRCLECI_JOB_NAME: &str = "CIRCLECI_JOB_NAME";
+/// Name of a built-in network (mainnet, testnet, localnet).
+pub const NETWORK: &str = "NETWORK";
+/// Path to a JSON network config, for custom networks.
+pub const NETWORK_CONFIG: &str = "NETWORK_CONFIG";
+/// Chain ID that the RPC node must report, e.g. the generated ID of a
+/// localnet.
+pub const CHAIN_ID: &str = "CHAIN_ID";
+/// Comma-separated RPC endpoints to fail over to, in order of preference.
+pub const FALLBACK_NODE_URLS: &str = "FALLBACK_NODE_URLS";
+pub const CONTRACT_ID: &str = "CONTRACT_ID";
+pub const ACCOUNT_ID: &str = "ACCOUNT_ID";
+/// Comma-separated list of secret keys for `ACCOUNT_ID`. Multiple keys allow
+/// transactions to be sent in parallel.
+pub const SECRET_KEY: &str = "SECRET_KEY";
+/// Directory of `near login` key files, e.g. `~/.near-credentials`. The key
+/// is read from `<dir>/<network>/<ACCOUNT_ID>.json`.
+pub const NEAR_CREDENTIALS_DIR: &str = "NEAR_CREDENTIALS_DIR";
+/// Passphrase-encrypted keystore created with `keystore create`.
+pub const KEYSTORE_PATH: &str = "KEYSTORE_PATH";
+pub const KEYSTORE_PASSPHRASE: &str = "KEYSTORE_PASSPHRASE";
+/// Unix socket of a process that signs transactions on the service's behalf.
+pub const EXTERNAL_SIGNER_SOCKET: &str = "EXTERNAL_SIGNER_SOCKET";
+/// Comma-separated list of the public keys the external signer holds.
+pub const EXTERNAL_SIGNER_PUBLIC_KEYS: &str = "EXTERNAL_SIGNER_PUBLIC_KEYS";
+pub const REPOSITORY_PATH: &str = "REPOSITORY_PATH";
+/// Remote of the CI repository to push to (default `origin`).
+pub const REPOSITORY_REMOTE: &str = "REPOSITORY_REMOTE";
+/// Branch of the CI repository that CI builds (default `main`).
+pub const REPOSITORY_BRANCH: &str = "REPOSITORY_BRANCH";
+/// Private key for SSH remotes. The SSH agent is used if unset.
+pub const GIT_SSH_KEY_PATH: &str = "GIT_SSH_KEY_PATH";
+pub const GIT_SSH_KEY_PASSPHRASE: &str = "GIT_SSH_KEY_PASSPHRASE";
+/// Username for HTTPS remotes, if the token requires a specific one.
+pub const GIT_USERNAME: &str = "GIT_USERNAME";
+/// Password or access token for HTTPS remotes.
+pub const GIT_TOKEN: &str = "GIT_TOKEN";
+/// Path to a JSON file of per-method gas and deposit overrides.
+pub const CALL_PROFILES: &str = "CALL_PROFILES";
+/// `text` (default) or `json`.
+pub const LOG_FORMAT: &str = "LOG_FORMAT";
+/// Collector to export traces to; requires the `otel` feature.
+pub const OTEL_EXPORTER_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// File in which builds in flight are recorded, to resume them after a restart.
+pub const STATE_PATH: &str = "STATE_PATH";
+/// Append-only log of request events, kept for the admin API.
+pub const JOURNAL_PATH: &str = "JOURNAL_PATH";
+/// Directory in which build logs are kept, named by their hash.
+pub const LOGS_PATH: &str = "LOGS_PATH";
+/// Base URL that the service is publicly reachable at, used in the build
+/// log URLs that are recorded on chain.
+pub const PUBLIC_URL: &str = "PUBLIC_URL";
+/// Comma-separated list of bearer tokens for the admin API, to allow rotation.
+pub const ADMIN_TOKENS: &str = "ADMIN_TOKENS";
+/// Base64-encoded 32-byte key that repository credentials are encrypted
+/// with. Enables verification of private repositories.
+pub const REPOSITORY_CREDENTIALS_KEY: &str = "REPOSITORY_CREDENTIALS_KEY";
+/// File in which encrypted repository credentials are kept.
+pub const CREDENTIALS_PATH: &str = "CREDENTIALS_PATH";
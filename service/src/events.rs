@@ -0,0 +1,177 @@
+use model::{code_hash::CodeHash, verification::VerificationRequest};
+use near_primitives::hash::CryptoHash;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{
+    broadcast::{self, Receiver, Sender},
+    watch,
+};
+
+/// Number of events a slow subscriber may fall behind before it starts
+/// missing events.
+const CAPACITY: usize = 256;
+
+/// Progress of a verification request, as observed by the service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub request_id: u64,
+    /// Unknown for requests made before requesters were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requester: Option<String>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    RequestCreated {
+        request: VerificationRequest,
+    },
+    BuildStarted {
+        /// Commit to the CI repository that triggered the build
+        commit: String,
+    },
+    BuildFinished {
+        job_number: u64,
+        status: String,
+        code_hash: Option<CodeHash>,
+        /// Hash of the stored build log, if it could be captured
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        log_hash: Option<String>,
+    },
+    Resolved {
+        verified: bool,
+        code_hash: Option<CodeHash>,
+        /// Resolution transaction, unless the request turned out to be
+        /// resolved already
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transaction_hash: Option<CryptoHash>,
+    },
+}
+
+impl Event {
+    pub fn new(request: &VerificationRequest, kind: EventKind) -> Self {
+        Self {
+            request_id: request.id,
+            requester: request.requester.as_ref().map(ToString::to_string),
+            kind,
+        }
+    }
+
+    /// Name of the event, e.g. for the SSE `event` field.
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            EventKind::RequestCreated { .. } => "request_created",
+            EventKind::BuildStarted { .. } => "build_started",
+            EventKind::BuildFinished { .. } => "build_finished",
+            EventKind::Resolved { .. } => "resolved",
+        }
+    }
+}
+
+/// Selects the events of a single request or requester.
+#[derive(Debug, Default, Deserialize)]
+pub struct EventFilter {
+    pub request_id: Option<u64>,
+    pub requester: Option<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        self.request_id.is_none_or(|id| id == event.request_id)
+            && self
+                .requester
+                .as_ref()
+                .is_none_or(|requester| event.requester.as_ref() == Some(requester))
+    }
+}
+
+/// Broadcasts events to any number of subscribers.
+#[derive(Clone)]
+pub struct Events {
+    sender: Sender<Event>,
+    closed: watch::Sender<bool>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+            closed: watch::channel(false).0,
+        }
+    }
+}
+
+impl Events {
+    pub fn publish(&self, event: Event) {
+        // Fails only if nobody is listening
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Signals subscribers that no more events will be published, e.g. so
+    /// that long-lived streams end during shutdown.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+
+    /// Completes once [`Events::close`] has been called.
+    pub async fn closed(&self) {
+        let mut closed = self.closed.subscribe();
+        // Errors only if the sender is dropped, which also means closed
+        let _ = closed.wait_for(|closed| *closed).await;
+    }
+}
+
+/// A pending request, for tests of anything that handles requests.
+#[cfg(test)]
+pub(crate) fn test_request(id: u64, requester: &str) -> VerificationRequest {
+    use model::verification::VerificationStatus;
+
+    VerificationRequest {
+        id,
+        requester: Some(requester.parse().unwrap()),
+        repository: "https://github.com/example/contract.git".to_string(),
+        path: "".to_string(),
+        checkout: "main".to_string(),
+        fee: 0.into(),
+        status: VerificationStatus::PENDING,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_request, Event, EventFilter, EventKind};
+
+    #[test]
+    fn filter_by_request_and_requester() {
+        let event = Event::new(
+            &test_request(3, "alice.near"),
+            EventKind::BuildStarted {
+                commit: "abc".to_string(),
+            },
+        );
+
+        assert!(EventFilter::default().matches(&event));
+        assert!(EventFilter {
+            request_id: Some(3),
+            requester: Some("alice.near".to_string()),
+        }
+        .matches(&event));
+        assert!(!EventFilter {
+            request_id: Some(4),
+            requester: None,
+        }
+        .matches(&event));
+        assert!(!EventFilter {
+            request_id: None,
+            requester: Some("bob.near".to_string()),
+        }
+        .matches(&event));
+    }
+}
@@ -0,0 +1,16 @@
+pub mod admin;
+pub mod api;
+pub mod call_profiles;
+pub mod circleci;
+pub mod cli;
+pub mod config;
+pub mod contract_interaction;
+pub mod env;
+pub mod events;
+pub mod monitoring;
+pub mod network_config;
+pub mod pipeline;
+pub mod rejection;
+pub mod repository;
+pub mod signer;
+pub mod telemetry;
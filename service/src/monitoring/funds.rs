@@ -0,0 +1,346 @@
+//! Whether the signer account can pay for resolving requests.
+//!
+//! Resolution calls that run out of funds fail long after the build that
+//! they record, so builds are only started while funds cover the more
+//! expensive of the two resolution calls.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use near_crypto::PublicKey;
+use near_primitives::{
+    types::{AccountId, Balance},
+    views::AccessKeyPermissionView,
+};
+use thiserror::Error;
+use tokio::sync::watch::Receiver;
+use tracing::{debug, warn};
+
+use crate::contract_interaction::{
+    nonce::can_sign, registry::RegistryClient, view, ContractInteractionError,
+};
+
+pub const ONE_NEAR: Balance = 10u128.pow(24);
+
+/// Balance locked per byte of account storage (`storage_amount_per_byte` in
+/// the mainnet and testnet genesis configuration).
+const STORAGE_AMOUNT_PER_BYTE: Balance = 10u128.pow(19);
+
+const RESOLUTION_METHODS: [&str; 2] = ["verification_success", "verification_failure"];
+
+pub fn format_near(amount: Balance) -> String {
+    format!("{} NEAR", amount as f64 / ONE_NEAR as f64)
+}
+
+#[derive(Debug, Clone)]
+pub struct FundsConfig {
+    pub check_interval: Duration,
+    /// Warn when the available balance drops below this
+    pub warn_balance: Balance,
+    /// Warn when the allowance of a function call key drops below this
+    pub warn_allowance: Balance,
+}
+
+impl Default for FundsConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(60),
+            warn_balance: 5 * ONE_NEAR,
+            warn_allowance: ONE_NEAR / 2,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FundsError {
+    #[error("Could not check signer funds: {0}")]
+    Query(String),
+    #[error(
+        "Available balance ({}) cannot cover resolution ({})",
+        format_near(*available),
+        format_near(*cost)
+    )]
+    Balance { available: Balance, cost: Balance },
+    #[error(
+        "Allowance of key {public_key} ({}) cannot cover resolution gas ({})",
+        format_near(*allowance),
+        format_near(*cost)
+    )]
+    Allowance {
+        public_key: PublicKey,
+        allowance: Balance,
+        cost: Balance,
+    },
+    #[error("No signer key can attach the resolution deposit ({})", format_near(*deposit))]
+    NoResolutionKey { deposit: Balance },
+}
+
+impl From<ContractInteractionError> for FundsError {
+    fn from(e: ContractInteractionError) -> Self {
+        Self::Query(e.to_string())
+    }
+}
+
+/// Upper bound of the cost of a resolution call, at the current gas price.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolutionCost {
+    /// Prepaid gas. Function call keys pay this from their allowance.
+    pub gas: Balance,
+    pub deposit: Balance,
+}
+
+impl ResolutionCost {
+    pub fn total(&self) -> Balance {
+        self.gas + self.deposit
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyFunds {
+    pub public_key: PublicKey,
+    pub full_access: bool,
+    /// `None` for full access keys and unlimited function call keys
+    pub allowance: Option<Balance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Funds {
+    pub balance: Balance,
+    /// Balance that is not locked for storage
+    pub available: Balance,
+    pub keys: Vec<KeyFunds>,
+    pub resolution_cost: ResolutionCost,
+}
+
+impl Funds {
+    pub fn ensure_covers_resolution(&self) -> Result<(), FundsError> {
+        let cost = self.resolution_cost;
+
+        if self.available < cost.total() {
+            return Err(FundsError::Balance {
+                available: self.available,
+                cost: cost.total(),
+            });
+        }
+
+        // Keys that can sign resolution calls are used round-robin, so every
+        // one of them must suffice
+        let keys: Vec<_> = self
+            .keys
+            .iter()
+            .filter(|key| can_sign(key.full_access, cost.deposit))
+            .collect();
+        if keys.is_empty() {
+            return Err(FundsError::NoResolutionKey {
+                deposit: cost.deposit,
+            });
+        }
+        for key in keys {
+            match key.allowance {
+                Some(allowance) if allowance < cost.gas => {
+                    return Err(FundsError::Allowance {
+                        public_key: key.public_key.clone(),
+                        allowance,
+                        cost: cost.gas,
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Periodically checks the balance of the signer account and the allowances
+/// of its keys.
+#[derive(Clone)]
+pub struct FundsMonitor {
+    registry: RegistryClient,
+    signer_id: AccountId,
+    config: FundsConfig,
+    latest: Arc<RwLock<Option<Funds>>>,
+}
+
+impl FundsMonitor {
+    pub fn new(registry: RegistryClient, signer_id: AccountId, config: FundsConfig) -> Self {
+        Self {
+            registry,
+            signer_id,
+            config,
+            latest: Default::default(),
+        }
+    }
+
+    pub fn signer_id(&self) -> &AccountId {
+        &self.signer_id
+    }
+
+    /// Result of the most recent successful check.
+    pub fn latest(&self) -> Option<Funds> {
+        self.latest.read().unwrap().clone()
+    }
+
+    async fn query(&self) -> Result<Funds, ContractInteractionError> {
+        let rpc = self.registry.rpc();
+
+        let (account, gas_price) = futures::try_join!(
+            view::account(rpc, self.signer_id.clone()),
+            view::gas_price(rpc),
+        )?;
+
+        let keys = futures::future::try_join_all(self.registry.signer_keys().into_iter().map(
+            |public_key| async move {
+                let access_key =
+                    view::access_key(rpc, self.signer_id.clone(), public_key.clone()).await?;
+                let (full_access, allowance) = match access_key.permission {
+                    AccessKeyPermissionView::FunctionCall { allowance, .. } => (false, allowance),
+                    AccessKeyPermissionView::FullAccess => (true, None),
+                };
+                Ok::<_, ContractInteractionError>(KeyFunds {
+                    public_key,
+                    full_access,
+                    allowance,
+                })
+            },
+        ))
+        .await?;
+
+        let resolution_cost = RESOLUTION_METHODS
+            .iter()
+            .map(|method| {
+                let profile = self.registry.call_profile(method);
+                ResolutionCost {
+                    gas: Balance::from(profile.gas) * gas_price,
+                    deposit: profile.deposit,
+                }
+            })
+            .max_by_key(ResolutionCost::total)
+            .unwrap();
+
+        let locked = Balance::from(account.storage_usage) * STORAGE_AMOUNT_PER_BYTE;
+
+        Ok(Funds {
+            balance: account.amount,
+            available: account.amount.saturating_sub(locked),
+            keys,
+            resolution_cost,
+        })
+    }
+
+    /// Queries current funds, records them for metrics, and warns if they are
+    /// below the configured thresholds.
+    pub async fn check(&self) -> Result<Funds, ContractInteractionError> {
+        let funds = self.query().await?;
+        *self.latest.write().unwrap() = Some(funds.clone());
+
+        debug!(
+            balance = %format_near(funds.balance),
+            available = %format_near(funds.available),
+            "Checked signer funds"
+        );
+
+        if funds.available < self.config.warn_balance {
+            warn!(
+                account = %self.signer_id,
+                available = %format_near(funds.available),
+                threshold = %format_near(self.config.warn_balance),
+                "Signer balance is low"
+            );
+        }
+        for key in &funds.keys {
+            if let Some(allowance) = key.allowance {
+                if allowance < self.config.warn_allowance {
+                    warn!(
+                        public_key = %key.public_key,
+                        allowance = %format_near(allowance),
+                        threshold = %format_near(self.config.warn_allowance),
+                        "Access key allowance is low"
+                    );
+                }
+            }
+        }
+        if let Err(e) = funds.ensure_covers_resolution() {
+            warn!("{e}; new builds will not be started");
+        }
+
+        Ok(funds)
+    }
+
+    /// Fails unless current funds cover resolving another request.
+    pub async fn ensure_can_resolve(&self) -> Result<(), FundsError> {
+        self.check().await?.ensure_covers_resolution()
+    }
+
+    /// Checks funds every [`FundsConfig::check_interval`] until `shutdown`
+    /// changes.
+    pub async fn run(&self, mut shutdown: Receiver<bool>) {
+        let mut interval = tokio::time::interval(self.config.check_interval);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = interval.tick() => {
+                    if let Err(e) = self.check().await {
+                        warn!("Could not check signer funds: {e}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, SecretKey};
+
+    use super::{Funds, FundsError, KeyFunds, ResolutionCost, ONE_NEAR};
+
+    #[test]
+    fn covers_resolution() {
+        let public_key = SecretKey::from_seed(KeyType::ED25519, "a").public_key();
+        let mut funds = Funds {
+            balance: 2 * ONE_NEAR,
+            available: ONE_NEAR,
+            keys: vec![KeyFunds {
+                public_key,
+                full_access: false,
+                allowance: Some(ONE_NEAR / 4),
+            }],
+            resolution_cost: ResolutionCost {
+                gas: ONE_NEAR / 10,
+                deposit: 0,
+            },
+        };
+        assert!(funds.ensure_covers_resolution().is_ok());
+
+        funds.keys[0].allowance = Some(ONE_NEAR / 20);
+        assert!(matches!(
+            funds.ensure_covers_resolution(),
+            Err(FundsError::Allowance { .. })
+        ));
+
+        // Function call keys can't attach the deposit, so don't count
+        funds.resolution_cost.deposit = ONE_NEAR / 100;
+        assert!(matches!(
+            funds.ensure_covers_resolution(),
+            Err(FundsError::NoResolutionKey { .. })
+        ));
+        let full_access = SecretKey::from_seed(KeyType::ED25519, "b").public_key();
+        funds.keys.push(KeyFunds {
+            public_key: full_access,
+            full_access: true,
+            allowance: None,
+        });
+        assert!(funds.ensure_covers_resolution().is_ok());
+
+        funds.keys.remove(0);
+        funds.available = ONE_NEAR / 10;
+        assert!(matches!(
+            funds.ensure_covers_resolution(),
+            Err(FundsError::Balance { .. })
+        ));
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Counters of events observed by the service. Values that come from the
+/// chain are read when metrics are scraped instead.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    webhooks_received: AtomicU64,
+    webhooks_rejected: AtomicU64,
+    artifacts_downloaded: AtomicU64,
+}
+
+impl Metrics {
+    pub fn webhook_received(&self) {
+        self.inner.webhooks_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn webhook_rejected(&self) {
+        self.inner.webhooks_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn artifacts_downloaded(&self) {
+        self.inner
+            .artifacts_downloaded
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn write_counters(&self, out: &mut Exposition) {
+        out.metric(
+            "webhooks_received_total",
+            "counter",
+            "Webhooks received from CI",
+            [(
+                String::new(),
+                self.inner.webhooks_received.load(Ordering::Relaxed) as f64,
+            )],
+        );
+        out.metric(
+            "webhooks_rejected_total",
+            "counter",
+            "Webhooks that failed validation or processing",
+            [(
+                String::new(),
+                self.inner.webhooks_rejected.load(Ordering::Relaxed) as f64,
+            )],
+        );
+        out.metric(
+            "artifacts_downloaded_total",
+            "counter",
+            "Sets of build artifacts downloaded from CI",
+            [(
+                String::new(),
+                self.inner.artifacts_downloaded.load(Ordering::Relaxed) as f64,
+            )],
+        );
+    }
+}
+
+/// Prometheus text exposition format.
+#[derive(Default)]
+pub(super) struct Exposition(String);
+
+const PREFIX: &str = "contract_registry_";
+
+impl Exposition {
+    /// Writes one metric. Each sample is a set of labels (e.g.
+    /// `method="a"`, or empty) and a value.
+    pub fn metric(
+        &mut self,
+        name: &str,
+        kind: &str,
+        help: &str,
+        samples: impl IntoIterator<Item = (String, f64)>,
+    ) {
+        let out = &mut self.0;
+        writeln!(out, "# HELP {PREFIX}{name} {help}").unwrap();
+        writeln!(out, "# TYPE {PREFIX}{name} {kind}").unwrap();
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                writeln!(out, "{PREFIX}{name} {value}").unwrap();
+            } else {
+                writeln!(out, "{PREFIX}{name}{{{labels}}} {value}").unwrap();
+            }
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Exposition, Metrics};
+
+    #[test]
+    fn renders_counters() {
+        let metrics = Metrics::default();
+        metrics.webhook_received();
+        metrics.webhook_received();
+        metrics.webhook_rejected();
+
+        let mut out = Exposition::default();
+        metrics.write_counters(&mut out);
+        let text = out.into_string();
+
+        assert!(text.contains("# TYPE contract_registry_webhooks_received_total counter\n"));
+        assert!(text.contains("\ncontract_registry_webhooks_received_total 2\n"));
+        assert!(text.contains("\ncontract_registry_webhooks_rejected_total 1\n"));
+        assert!(text.contains("\ncontract_registry_artifacts_downloaded_total 0\n"));
+    }
+}
@@ -0,0 +1,290 @@
+//! Liveness, readiness and Prometheus metrics endpoints.
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+use serde::Serialize;
+use tokio::sync::watch::Receiver;
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+use crate::{
+    circleci::client::check_access,
+    contract_interaction::{registry::RegistryClient, view},
+};
+
+pub use self::metrics::Metrics;
+use self::{funds::FundsMonitor, metrics::Exposition};
+
+pub mod funds;
+mod metrics;
+
+const YOCTO_PER_NEAR: f64 = 1e24;
+
+/// How often readiness is checked. Probes are served the latest result, so
+/// that they do not query RPC and CircleCI themselves.
+pub const READINESS_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Everything the monitoring endpoints inspect.
+#[derive(Clone)]
+pub struct Monitor {
+    pub registry: RegistryClient,
+    pub funds: FundsMonitor,
+    pub circleci: Client,
+    pub metrics: Metrics,
+    readiness: Arc<RwLock<Option<Readiness>>>,
+}
+
+#[derive(Serialize, Clone)]
+struct Check {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<E: ToString> From<Result<(), E>> for Check {
+    fn from(result: Result<(), E>) -> Self {
+        Self {
+            ok: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct Readiness {
+    ready: bool,
+    rpc: Check,
+    signer: Check,
+    circleci: Check,
+}
+
+impl Monitor {
+    pub fn new(
+        registry: RegistryClient,
+        funds: FundsMonitor,
+        circleci: Client,
+        metrics: Metrics,
+    ) -> Self {
+        Self {
+            registry,
+            funds,
+            circleci,
+            metrics,
+            readiness: Default::default(),
+        }
+    }
+
+    async fn check_readiness(&self) -> Readiness {
+        let rpc = self.registry.rpc();
+
+        rpc.check_health().await;
+        let rpc_check = Check::from(
+            if rpc.endpoint_health().iter().any(|(_, healthy)| *healthy) {
+                Ok(())
+            } else {
+                Err("No RPC endpoint is healthy")
+            },
+        );
+
+        let (signer, circleci) = futures::join!(
+            view::account(rpc, self.funds.signer_id().clone()),
+            check_access(&self.circleci),
+        );
+        let signer = Check::from(signer.map(|_| ()));
+        let circleci = Check::from(circleci);
+
+        Readiness {
+            ready: rpc_check.ok && signer.ok && circleci.ok,
+            rpc: rpc_check,
+            signer,
+            circleci,
+        }
+    }
+
+    /// Checks readiness every [`READINESS_CHECK_INTERVAL`] until `shutdown`
+    /// changes.
+    pub async fn run(&self, mut shutdown: Receiver<bool>) {
+        let mut interval = tokio::time::interval(READINESS_CHECK_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = interval.tick() => {
+                    let readiness = self.check_readiness().await;
+                    *self.readiness.write().unwrap() = Some(readiness);
+                }
+            }
+        }
+    }
+}
+
+async fn readiness(monitor: Monitor) -> Result<impl Reply, Infallible> {
+    let readiness = monitor.readiness.read().unwrap().clone();
+    let readiness = readiness.unwrap_or_else(|| {
+        let pending = || Check::from(Err("Not checked yet"));
+        Readiness {
+            ready: false,
+            rpc: pending(),
+            signer: pending(),
+            circleci: pending(),
+        }
+    });
+
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&readiness),
+        status,
+    ))
+}
+
+/// Seconds since the Unix epoch, from a NEAR block timestamp in nanoseconds.
+fn block_timestamp_secs(timestamp: u64) -> f64 {
+    timestamp as f64 / 1e9
+}
+
+async fn metrics(monitor: Monitor) -> Result<impl Reply, Infallible> {
+    let mut out = Exposition::default();
+
+    monitor.metrics.write_counters(&mut out);
+
+    let call_costs = monitor.registry.call_costs();
+    out.metric(
+        "transactions_sent_total",
+        "counter",
+        "Transactions sent to the registry contract",
+        call_costs.iter().map(|(method, costs)| {
+            (
+                format!("method=\"{method}\""),
+                (costs.calls + costs.failures) as f64,
+            )
+        }),
+    );
+    out.metric(
+        "transactions_failed_total",
+        "counter",
+        "Transactions to the registry contract that did not succeed",
+        call_costs
+            .iter()
+            .map(|(method, costs)| (format!("method=\"{method}\""), costs.failures as f64)),
+    );
+    out.metric(
+        "gas_burnt_total",
+        "counter",
+        "Gas burnt by successful transactions",
+        call_costs
+            .iter()
+            .map(|(method, costs)| (format!("method=\"{method}\""), costs.gas_burnt as f64)),
+    );
+
+    out.metric(
+        "rpc_endpoint_healthy",
+        "gauge",
+        "Whether the RPC endpoint was healthy when last used",
+        monitor
+            .registry
+            .rpc()
+            .endpoint_health()
+            .into_iter()
+            .map(|(url, healthy)| (format!("url=\"{url}\""), f64::from(u8::from(healthy)))),
+    );
+
+    if let Ok(pending) = monitor.registry.get_pending_requests().await {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        let oldest = pending.iter().map(|r| r.created_at).min();
+
+        out.metric(
+            "pending_requests",
+            "gauge",
+            "Verification requests waiting to be resolved",
+            [(String::new(), pending.len() as f64)],
+        );
+        out.metric(
+            "pending_request_lag_seconds",
+            "gauge",
+            "Age of the oldest pending verification request",
+            [(
+                String::new(),
+                oldest.map_or(0.0, |t| (now - block_timestamp_secs(t)).max(0.0)),
+            )],
+        );
+    }
+
+    // Checked periodically rather than on every scrape
+    if let Some(funds) = monitor.funds.latest() {
+        let account = format!("account=\"{}\"", monitor.funds.signer_id());
+
+        out.metric(
+            "signer_balance_near",
+            "gauge",
+            "Balance of the signer account",
+            [(account.clone(), funds.balance as f64 / YOCTO_PER_NEAR)],
+        );
+        out.metric(
+            "signer_available_balance_near",
+            "gauge",
+            "Balance of the signer account that is not locked for storage",
+            [(account.clone(), funds.available as f64 / YOCTO_PER_NEAR)],
+        );
+        out.metric(
+            "signer_key_allowance_near",
+            "gauge",
+            "Remaining allowance of each function call key with limited allowance",
+            funds.keys.iter().filter_map(|key| {
+                key.allowance.map(|allowance| {
+                    (
+                        format!("{account},public_key=\"{}\"", key.public_key),
+                        allowance as f64 / YOCTO_PER_NEAR,
+                    )
+                })
+            }),
+        );
+        out.metric(
+            "resolution_cost_near",
+            "gauge",
+            "Upper bound of the cost of resolving a request at the current gas price",
+            [(
+                String::new(),
+                funds.resolution_cost.total() as f64 / YOCTO_PER_NEAR,
+            )],
+        );
+        out.metric(
+            "signer_funds_sufficient",
+            "gauge",
+            "Whether funds cover resolving another request (builds are not started otherwise)",
+            [(
+                account,
+                f64::from(u8::from(funds.ensure_covers_resolution().is_ok())),
+            )],
+        );
+    }
+
+    Ok(warp::reply::with_header(
+        out.into_string(),
+        "Content-Type",
+        "text/plain; version=0.0.4",
+    ))
+}
+
+pub fn routes(monitor: Monitor) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let monitor = warp::any().map(move || monitor.clone());
+
+    let healthz = warp::path!("healthz").map(|| "ok");
+    let readyz = warp::path!("readyz")
+        .and(monitor.clone())
+        .and_then(readiness);
+    let metrics = warp::path!("metrics").and(monitor).and_then(metrics);
+
+    warp::get().and(healthz.or(readyz).or(metrics))
+}
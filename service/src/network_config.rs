@@ -1,23 +1,124 @@
 use std::fs;
 
+use near_jsonrpc_client::methods;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::contract_interaction::rpc::RpcPool;
+
+/// Names of the built-in network configurations.
+pub const PRESETS: [&str; 3] = ["mainnet", "testnet", "localnet"];
+
+#[derive(Debug, Error)]
+pub enum NetworkCheckError {
+    #[error("Could not query node status: {0}")]
+    Status(String),
+    #[error("Expected chain ID {expected:?} does not match the RPC node's chain ID {actual:?}")]
+    Mismatch { expected: String, actual: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NetworkConfig {
     pub network_id: String,
+    /// Chain ID that the RPC node must report. Defaults to `network_id`,
+    /// except for localnet, whose chain ID is generated when the node is
+    /// initialized.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<String>,
     pub node_url: String,
+    /// Additional RPC endpoints to fail over to if `node_url` is unavailable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_node_urls: Vec<String>,
     pub archival_url: String,
     pub wallet_url: String,
     pub helper_url: String,
     pub explorer_url: String,
 }
 
-pub fn load(path: &str) -> NetworkConfig {
-    let handle = fs::File::open(path).expect(&format!(
-        "FATAL: Could not load network config path: {path}"
-    ));
+impl NetworkConfig {
+    /// All RPC endpoints, in order of preference.
+    pub fn rpc_urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.node_url.as_str())
+            .chain(self.fallback_node_urls.iter().map(String::as_str))
+    }
+
+    /// `None` if any chain ID is accepted.
+    pub fn expected_chain_id(&self) -> Option<&str> {
+        match (&self.chain_id, self.network_id.as_str()) {
+            (Some(chain_id), _) => Some(chain_id),
+            (None, "localnet") => None,
+            (None, network_id) => Some(network_id),
+        }
+    }
+
+    /// Ensures that the RPC node serves the configured network.
+    pub async fn check_chain_id(&self, rpc: &RpcPool) -> Result<(), NetworkCheckError> {
+        let Some(expected) = self.expected_chain_id() else {
+            return Ok(());
+        };
+
+        let status = rpc
+            .call(methods::status::RpcStatusRequest)
+            .await
+            .map_err(|e| NetworkCheckError::Status(e.to_string()))?;
+
+        if status.chain_id == expected {
+            Ok(())
+        } else {
+            Err(NetworkCheckError::Mismatch {
+                expected: expected.to_string(),
+                actual: status.chain_id,
+            })
+        }
+    }
+}
+
+/// Returns one of the built-in network configurations by name.
+pub fn preset(name: &str) -> Option<NetworkConfig> {
+    let json = match name {
+        "mainnet" => include_str!("../network/mainnet.json"),
+        "testnet" => include_str!("../network/testnet.json"),
+        "localnet" => include_str!("../network/localnet.json"),
+        _ => return None,
+    };
+
+    Some(serde_json::from_str(json).expect("Built-in network config is valid"))
+}
+
+pub fn load(path: &str) -> Result<NetworkConfig, String> {
+    let handle = fs::File::open(path)
+        .map_err(|e| format!("Could not load network config path {path}: {e}"))?;
     let reader = std::io::BufReader::new(handle);
 
-    serde_json::from_reader(reader).expect("FATAL: Could not parse network config file")
+    serde_json::from_reader(reader)
+        .map_err(|e| format!("Could not parse network config file {path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{preset, PRESETS};
+
+    #[test]
+    fn localnet_accepts_any_chain_id() {
+        assert_eq!(
+            preset("testnet").unwrap().expected_chain_id(),
+            Some("testnet")
+        );
+
+        let mut localnet = preset("localnet").unwrap();
+        assert_eq!(localnet.expected_chain_id(), None);
+        localnet.chain_id = Some("test-chain-7ntZ8".to_string());
+        assert_eq!(localnet.expected_chain_id(), Some("test-chain-7ntZ8"));
+    }
+
+    #[test]
+    fn presets_are_valid() {
+        for name in PRESETS {
+            let config = preset(name).expect("Preset exists");
+            assert_eq!(config.network_id, name);
+        }
+
+        assert!(preset("betanet").is_none());
+    }
 }
@@ -0,0 +1,382 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use model::verification::{BuildLog, VerificationRequest};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::error;
+
+use crate::contract_interaction::SentTransaction;
+
+#[derive(Debug, Error)]
+pub enum StateError {
+    #[error("Could not read state file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not parse state file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// A CI job that finished building a request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompletedJob {
+    pub number: u64,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Build {
+    pub request: VerificationRequest,
+    /// Commit to the CI repository that triggered the build
+    pub commit: String,
+    /// Set once the job finishes, before the request is resolved on chain
+    pub completed_job: Option<CompletedJob>,
+    /// Output of the completed job, once stored, so that retries of the
+    /// resolution do not fetch it again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<BuildLog>,
+    /// Resolution transaction that timed out, and may still land on chain.
+    /// Its outcome is checked before another one is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unconfirmed: Option<SentTransaction>,
+    /// Failed attempts to resolve the request
+    #[serde(default)]
+    pub attempts: u32,
+    /// Unix timestamp (seconds) before which resolution is not retried
+    #[serde(default)]
+    pub retry_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Resolution was given up on. Such builds are kept until they are
+    /// replayed, so that the request is not built again.
+    #[serde(default)]
+    pub dead_letter: bool,
+}
+
+/// Builds that have been triggered but not yet resolved, by the commit that
+/// triggered them. Every change is written to disk, so that builds in flight
+/// during a restart can be resumed.
+#[derive(Clone)]
+pub struct BuildTracker {
+    path: Arc<PathBuf>,
+    builds: Arc<Mutex<HashMap<String, Build>>>,
+}
+
+impl BuildTracker {
+    fn read(path: &Path) -> Result<HashMap<String, Build>, StateError> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|source| StateError::Parse {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(source) => Err(StateError::Read {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Loads tracked builds from `path`, which need not exist yet.
+    pub fn open(path: &Path) -> Result<Self, StateError> {
+        Ok(Self {
+            path: Arc::new(path.to_path_buf()),
+            builds: Arc::new(Mutex::new(Self::read(path)?)),
+        })
+    }
+
+    /// Takes an advisory lock on a file next to the state, held until the
+    /// returned file is dropped. The state file itself is replaced on every
+    /// save, so can't be locked.
+    fn lock(&self) -> std::io::Result<File> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_extension("lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Writes to a temporary file first, so that a crash mid-write doesn't
+    /// corrupt the state. The temporary file is named after the process, as
+    /// admin commands save the same state.
+    fn save(&self, builds: &HashMap<String, Build>) {
+        let tmp = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        let result = serde_json::to_vec_pretty(builds)
+            .map_err(std::io::Error::from)
+            .and_then(|json| fs::write(&tmp, json))
+            .and_then(|()| fs::rename(&tmp, self.path.as_ref()));
+
+        if let Err(e) = result {
+            error!("Could not save state to {}: {e}", self.path.display());
+        }
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut HashMap<String, Build>) -> T) -> T {
+        let mut builds = self.builds.lock().unwrap();
+        // Admin commands run in a separate process and change the file
+        // directly, so it is locked from reading until the change is saved
+        let _lock = self
+            .lock()
+            .inspect_err(|e| error!("Could not lock state: {e}"))
+            .ok();
+        match Self::read(&self.path) {
+            Ok(on_disk) => *builds = on_disk,
+            Err(e) => error!("Could not reload state: {e}"),
+        }
+        let result = f(&mut builds);
+        self.save(&builds);
+        result
+    }
+
+    pub fn start(&self, commit: String, request: VerificationRequest) {
+        self.update(|builds| {
+            builds.insert(
+                commit.clone(),
+                Build {
+                    request,
+                    commit,
+                    completed_job: None,
+                    log: None,
+                    unconfirmed: None,
+                    attempts: 0,
+                    retry_at: 0,
+                    last_error: None,
+                    dead_letter: false,
+                },
+            );
+        });
+    }
+
+    pub fn get(&self, commit: &str) -> Option<Build> {
+        self.builds.lock().unwrap().get(commit).cloned()
+    }
+
+    /// Whether a build has already been triggered for the request.
+    pub fn contains_request(&self, request_id: u64) -> bool {
+        self.builds
+            .lock()
+            .unwrap()
+            .values()
+            .any(|b| b.request.id == request_id)
+    }
+
+    /// Records that the job finished. Returns the updated build.
+    pub fn complete(&self, commit: &str, job: CompletedJob) -> Option<Build> {
+        self.update(|builds| {
+            let build = builds.get_mut(commit)?;
+            build.completed_job = Some(job);
+            Some(build.clone())
+        })
+    }
+
+    pub fn set_log(&self, commit: &str, log: BuildLog) {
+        self.update(|builds| {
+            if let Some(build) = builds.get_mut(commit) {
+                build.log = Some(log);
+            }
+        });
+    }
+
+    pub fn set_unconfirmed(&self, commit: &str, transaction: Option<SentTransaction>) {
+        self.update(|builds| {
+            if let Some(build) = builds.get_mut(commit) {
+                build.unconfirmed = transaction;
+            }
+        });
+    }
+
+    /// Builds whose job finished but whose request may not be resolved yet,
+    /// and that are due to be (re)tried at `now`.
+    pub fn unresolved(&self, now: u64) -> Vec<Build> {
+        self.builds
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|b| b.completed_job.is_some() && !b.dead_letter && b.retry_at <= now)
+            .cloned()
+            .collect()
+    }
+
+    /// Records a failed attempt to resolve the build. It is retried after
+    /// `retry_at`, or moved to the dead letters if that is `None`.
+    pub fn fail(&self, commit: &str, error: String, retry_at: Option<u64>) {
+        self.update(|builds| {
+            if let Some(build) = builds.get_mut(commit) {
+                build.attempts += 1;
+                build.last_error = Some(error);
+                build.retry_at = retry_at.unwrap_or_default();
+                build.dead_letter = retry_at.is_none();
+            }
+        });
+    }
+
+    /// Tracked builds matching `filter`, as currently on disk, by request.
+    fn current(&self, filter: impl Fn(&Build) -> bool) -> Vec<Build> {
+        let mut builds = Self::read(&self.path)
+            .unwrap_or_else(|_| self.builds.lock().unwrap().clone())
+            .into_values()
+            .filter(filter)
+            .collect::<Vec<_>>();
+        builds.sort_by_key(|b| b.request.id);
+        builds
+    }
+
+    pub fn dead_letters(&self) -> Vec<Build> {
+        self.current(|b| b.dead_letter)
+    }
+
+    pub fn for_request(&self, request_id: u64) -> Vec<Build> {
+        self.current(|b| b.request.id == request_id)
+    }
+
+    /// Stops tracking every build of the request, so that their jobs no
+    /// longer resolve it.
+    pub fn forget_request(&self, request_id: u64) {
+        self.update(|builds| builds.retain(|_, b| b.request.id != request_id));
+    }
+
+    /// Takes the build of a request out of the dead letters, with a fresh
+    /// set of attempts. Returns the build, if there was one.
+    pub fn replay(&self, request_id: u64) -> Option<Build> {
+        self.update(|builds| {
+            let build = builds
+                .values_mut()
+                .find(|b| b.request.id == request_id && b.dead_letter)?;
+            build.attempts = 0;
+            build.retry_at = 0;
+            build.dead_letter = false;
+            Some(build.clone())
+        })
+    }
+
+    pub fn finish(&self, commit: &str) {
+        self.update(|builds| {
+            builds.remove(commit);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use model::verification::BuildLog;
+    use near_primitives::hash::CryptoHash;
+
+    use crate::{contract_interaction::SentTransaction, events::test_request};
+
+    use super::{BuildTracker, CompletedJob};
+
+    #[test]
+    fn resumes_from_saved_state() {
+        let path = std::env::temp_dir().join(format!("builds-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let builds = BuildTracker::open(&path).unwrap();
+        builds.start("abc".to_string(), test_request(7, "alice.near"));
+        let job = CompletedJob {
+            number: 12,
+            status: "success".to_string(),
+        };
+        builds.complete("abc", job.clone());
+        let log = BuildLog {
+            hash: "ab".repeat(32),
+            url: None,
+        };
+        builds.set_log("abc", log.clone());
+        let transaction = SentTransaction {
+            hash: CryptoHash::default(),
+            signer_id: "registry.near".parse().unwrap(),
+            reference_block_hash: CryptoHash::default(),
+        };
+        builds.set_unconfirmed("abc", Some(transaction.clone()));
+
+        let reopened = BuildTracker::open(&path).unwrap();
+        assert!(reopened.contains_request(7));
+        let unresolved = reopened.unresolved(0);
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].completed_job, Some(job));
+        assert_eq!(unresolved[0].log, Some(log));
+        assert_eq!(unresolved[0].unconfirmed, Some(transaction));
+
+        reopened.finish("abc");
+        assert!(BuildTracker::open(&path).unwrap().unresolved(0).is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+    }
+
+    #[test]
+    fn retries_then_dead_letters() {
+        let path = std::env::temp_dir().join(format!("dead-letters-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let builds = BuildTracker::open(&path).unwrap();
+        builds.start("abc".to_string(), test_request(7, "alice.near"));
+        builds.complete(
+            "abc",
+            CompletedJob {
+                number: 12,
+                status: "success".to_string(),
+            },
+        );
+
+        builds.fail("abc", "RPC unavailable".to_string(), Some(100));
+        assert!(builds.unresolved(99).is_empty());
+        assert_eq!(builds.unresolved(100)[0].attempts, 1);
+
+        builds.fail("abc", "Execution error".to_string(), None);
+        assert!(builds.unresolved(u64::MAX).is_empty());
+        // Still tracked, so that the request is not built again
+        assert!(builds.contains_request(7));
+        let dead = builds.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].last_error.as_deref(), Some("Execution error"));
+
+        assert!(builds.replay(7).is_some());
+        assert!(builds.dead_letters().is_empty());
+        assert_eq!(builds.unresolved(0)[0].attempts, 0);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+    }
+
+    #[test]
+    fn separate_trackers_do_not_lose_changes() {
+        let path = std::env::temp_dir().join(format!("shared-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // e.g. the server and an admin command
+        let threads: Vec<_> = (0..2)
+            .map(|t| {
+                let builds = BuildTracker::open(&path).unwrap();
+                std::thread::spawn(move || {
+                    for i in 0..20 {
+                        let id = t * 100 + i;
+                        builds.start(format!("commit-{id}"), test_request(id, "alice.near"));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let builds = BuildTracker::open(&path).unwrap();
+        assert!((0..20).all(|i| builds.contains_request(i) && builds.contains_request(100 + i)));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("lock")).unwrap();
+    }
+}
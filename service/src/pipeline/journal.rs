@@ -0,0 +1,127 @@
+//! Append-only record of request events, one JSON object per line.
+//!
+//! Tracked builds are forgotten once their request is resolved, while the
+//! journal keeps the whole history, so that a request can be traced to the
+//! CI jobs and transactions that handled it.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::events::Event;
+
+use super::{builds::StateError, unix_now};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix timestamp (seconds)
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+#[derive(Clone)]
+pub struct Journal {
+    path: Arc<PathBuf>,
+    /// Keeps lines written by this process from interleaving
+    lock: Arc<Mutex<()>>,
+}
+
+impl Journal {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: Arc::new(path.to_path_buf()),
+            lock: Default::default(),
+        }
+    }
+
+    pub fn append(&self, event: &Event) {
+        let mut line = serde_json::to_vec(&JournalEntry {
+            at: unix_now(),
+            event: event.clone(),
+        })
+        .expect("Events are always serializable");
+        line.push(b'\n');
+
+        let _lock = self.lock.lock().unwrap();
+        let result = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.as_ref())
+            .and_then(|mut file| file.write_all(&line));
+
+        if let Err(e) = result {
+            error!("Could not append to journal {}: {e}", self.path.display());
+        }
+    }
+
+    /// Entries of a single request, oldest first.
+    pub fn for_request(&self, request_id: u64) -> Result<Vec<JournalEntry>, StateError> {
+        let text = match fs::read_to_string(self.path.as_ref()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(source) => {
+                return Err(StateError::Read {
+                    path: self.path.to_path_buf(),
+                    source,
+                })
+            }
+        };
+
+        Ok(text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| match serde_json::from_str::<JournalEntry>(line) {
+                Ok(entry) => Some(entry),
+                // e.g. a line cut short by a crash
+                Err(e) => {
+                    warn!("Skipping malformed journal entry: {e}");
+                    None
+                }
+            })
+            .filter(|entry| entry.event.request_id == request_id)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{test_request, Event, EventKind};
+
+    use super::Journal;
+
+    #[test]
+    fn filters_by_request() {
+        let path = std::env::temp_dir().join(format!("journal-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let journal = Journal::new(&path);
+        for id in [1, 2, 1] {
+            journal.append(&Event::new(
+                &test_request(id, "alice.near"),
+                EventKind::BuildStarted {
+                    commit: format!("commit-{id}"),
+                },
+            ));
+        }
+
+        let entries = journal.for_request(1).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(
+            &entries[0].event.kind,
+            EventKind::BuildStarted { commit } if commit == "commit-1"
+        ));
+        assert!(Journal::new(&path.with_extension("missing"))
+            .for_request(1)
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,998 @@
+//! Drives verification requests from the registry through CI and back:
+//! new requests trigger a build by committing to the CI repository, and the
+//! CI webhook for that commit resolves the request on chain.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use model::verification::{
+    BuildLog, Verification, VerificationFailure, VerificationRequest, VerificationStatus,
+};
+use near_primitives::hash::CryptoHash;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{sync::watch::Receiver, task::JoinError};
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+use warp::reject::Reject;
+
+use crate::{
+    circleci::{
+        client::{create_client, get_job_log, request_job, ParallelError, VerificationMetadata},
+        error::CircleCiError,
+        webhook::JobCompletedWebhookPayload,
+    },
+    config::Config,
+    contract_interaction::{
+        registry::RegistryClient, watch, ContractInteractionError, SentTransaction,
+    },
+    events::{Event, EventKind, Events},
+    monitoring::{
+        funds::{FundsError, FundsMonitor},
+        Metrics,
+    },
+    repository::{self, access::CredentialStore, RepositoryConfig},
+};
+
+use self::{
+    builds::{Build, BuildTracker, CompletedJob, StateError},
+    journal::{Journal, JournalEntry},
+    logs::LogStore,
+};
+
+pub mod builds;
+pub mod journal;
+pub mod logs;
+
+#[derive(Debug, Error)]
+pub enum PipelineError {
+    #[error("CircleCI error: {0}")]
+    CircleCi(#[from] ParallelError<CircleCiError>),
+    #[error("Could not resolve request {id}: {message}")]
+    Resolution {
+        id: u64,
+        message: String,
+        transient: bool,
+    },
+    #[error("Request {0} is not in the dead letters")]
+    NotDeadLettered(u64),
+    #[error("Request {0} does not exist")]
+    UnknownRequest(u64),
+    #[error("Request {id} is not pending ({status:?})")]
+    NotPending { id: u64, status: VerificationStatus },
+    #[error("Could not query the registry: {0}")]
+    Registry(String),
+    #[error(transparent)]
+    State(#[from] StateError),
+    #[error("Repository error: {0}")]
+    Repository(#[from] git2::Error),
+    #[error("Repository update did not complete: {0}")]
+    Join(#[from] JoinError),
+    #[error(transparent)]
+    Funds(#[from] FundsError),
+}
+
+impl Reject for PipelineError {}
+
+impl PipelineError {
+    fn resolution(id: u64, e: ContractInteractionError) -> Self {
+        Self::Resolution {
+            id,
+            message: e.to_string(),
+            transient: e.is_transient(),
+        }
+    }
+
+    /// Whether resolution might succeed if it is retried later.
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::CircleCi(ParallelError::TaskError(CircleCiError::JsonSchemaMismatch)) => false,
+            Self::CircleCi(_) => true,
+            Self::Resolution { transient, .. } => *transient,
+            Self::NotDeadLettered(_)
+            | Self::UnknownRequest(_)
+            | Self::NotPending { .. }
+            | Self::State(_) => false,
+            Self::Registry(_) | Self::Repository(_) | Self::Join(_) | Self::Funds(_) => true,
+        }
+    }
+}
+
+/// Outcome of a request chosen by an operator, e.g. because CI cannot build
+/// it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ManualResolution {
+    Success(Artifacts),
+    Failure,
+}
+
+/// Artifacts that a request is verified with.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Artifacts {
+    /// Those of a CircleCI job, by job number
+    Job(u64),
+    /// Given directly, e.g. from a build outside CI
+    #[serde(rename = "artifacts")]
+    Given(VerificationMetadata),
+}
+
+/// How a request is resolved on chain.
+enum Resolution {
+    Verified(VerificationMetadata),
+    Failed(Option<BuildLog>),
+}
+
+/// Everything known about a request: its state on chain, the builds
+/// tracked for it, and what the service recorded while handling it
+/// (including CI jobs and resolution transactions).
+#[derive(Debug, Serialize)]
+pub struct Correlation {
+    pub request: VerificationRequest,
+    pub verification: Option<Verification>,
+    pub failure: Option<VerificationFailure>,
+    pub builds: Vec<Build>,
+    /// Oldest first
+    pub events: Vec<JournalEntry>,
+}
+
+impl Correlation {
+    /// Does not need a [`Pipeline`], so that requests can be traced with a
+    /// read-only configuration.
+    pub async fn collect(
+        registry: &RegistryClient,
+        builds: &BuildTracker,
+        journal: &Journal,
+        request_id: u64,
+    ) -> Result<Self, PipelineError> {
+        let registry_error = |e: ContractInteractionError| PipelineError::Registry(e.to_string());
+        let (request, verification, failure) = futures::try_join!(
+            registry.get_verification_request(request_id),
+            registry.get_verification_result(request_id),
+            registry.get_verification_failure(request_id),
+        )
+        .map_err(registry_error)?;
+
+        Ok(Self {
+            request: request.ok_or(PipelineError::UnknownRequest(request_id))?,
+            verification,
+            failure,
+            builds: builds.for_request(request_id),
+            events: journal.for_request(request_id)?,
+        })
+    }
+}
+
+/// Contract panic message when a request is resolved twice, e.g. when an
+/// earlier attempt that timed out did land on chain after all.
+const ALREADY_RESOLVED: &str = "Request already resolved";
+
+/// Retries of a failed resolution or build trigger are at most this far
+/// apart.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// `base`, doubled for every attempt after the first, up to [`MAX_BACKOFF`].
+fn backoff(base: Duration, attempts: u32) -> Duration {
+    base.saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// A request whose build could not be triggered yet.
+struct Deferred {
+    request: VerificationRequest,
+    /// Failed attempts to trigger its build, other than for lack of funds
+    failures: u32,
+    /// Unix seconds
+    retry_at: u64,
+}
+
+/// Removes the oldest deferred request that is due for a retry at `now`.
+fn take_due(deferred: &mut VecDeque<Deferred>, now: u64) -> Option<Deferred> {
+    let index = deferred.iter().position(|d| d.retry_at <= now)?;
+    deferred.remove(index)
+}
+
+/// Whether `now` (Unix seconds) is within `wait` of `created_at`, a block
+/// timestamp in nanoseconds.
+fn within_wait(created_at: u64, wait: Duration, now: u64) -> bool {
+    now < (created_at / 1_000_000_000).saturating_add(wait.as_secs())
+}
+
+/// Whether a credential registered after `builds` were triggered may make a
+/// difference: some were triggered, and none succeeded.
+fn needs_rebuild(builds: &[Build]) -> bool {
+    let succeeded = builds
+        .iter()
+        .any(|build| matches!(&build.completed_job, Some(job) if job.status == "success"));
+    !builds.is_empty() && !succeeded
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[derive(Clone)]
+pub struct Pipeline {
+    registry: RegistryClient,
+    circleci: Client,
+    project_slug: String,
+    /// Only webhooks for this job resolve requests, if set
+    job_name: Option<String>,
+    repository: RepositoryConfig,
+    builds: BuildTracker,
+    journal: Journal,
+    logs: LogStore,
+    /// Set if private repositories are enabled
+    credentials: Option<CredentialStore>,
+    /// How long builds of new requests wait for a credential
+    credential_wait: Duration,
+    events: Events,
+    metrics: Metrics,
+    funds: FundsMonitor,
+    resolution_attempts: u32,
+    resolution_backoff: Duration,
+}
+
+impl Pipeline {
+    /// Loads builds that were in flight when the service last stopped.
+    ///
+    /// The configuration must have been loaded with
+    /// [`RequiredSections::ALL`](crate::config::RequiredSections::ALL).
+    pub fn new(config: &Config) -> Result<Self, StateError> {
+        let registry = config.registry_client();
+
+        Ok(Self {
+            funds: FundsMonitor::new(
+                registry.clone(),
+                config.signer().account_id.clone(),
+                config.funds.clone(),
+            ),
+            registry,
+            circleci: create_client(&config.circleci().api_key),
+            project_slug: config.circleci().project_slug.clone(),
+            job_name: config.circleci().job_name.clone(),
+            repository: config.repository().clone(),
+            builds: BuildTracker::open(&config.state_path)?,
+            journal: Journal::new(&config.journal_path),
+            logs: LogStore::new(&config.logs_path, config.public_url.as_deref()),
+            credentials: config
+                .private_repositories
+                .as_ref()
+                .map(|private| {
+                    CredentialStore::open(&private.credentials_path, &private.encryption_key)
+                })
+                .transpose()?,
+            credential_wait: config
+                .private_repositories
+                .as_ref()
+                .map_or(Duration::ZERO, |private| private.credential_wait),
+            resolution_attempts: config.limits.resolution_attempts,
+            resolution_backoff: config.limits.resolution_backoff,
+            events: Events::default(),
+            metrics: Metrics::default(),
+        })
+    }
+
+    pub fn registry(&self) -> &RegistryClient {
+        &self.registry
+    }
+
+    pub fn circleci(&self) -> &Client {
+        &self.circleci
+    }
+
+    pub fn events(&self) -> &Events {
+        &self.events
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    pub fn funds(&self) -> &FundsMonitor {
+        &self.funds
+    }
+
+    pub fn builds(&self) -> &BuildTracker {
+        &self.builds
+    }
+
+    pub fn logs(&self) -> &LogStore {
+        &self.logs
+    }
+
+    pub fn credentials(&self) -> Option<&CredentialStore> {
+        self.credentials.as_ref()
+    }
+
+    /// Publishes the event and appends it to the journal.
+    fn record(&self, event: Event) {
+        self.journal.append(&event);
+        self.events.publish(event);
+    }
+
+    /// Commits the request to the CI repository, which starts a build.
+    /// Returns the hash of the commit.
+    ///
+    /// Fails without starting a build if the signer could not pay to resolve
+    /// the request.
+    #[tracing::instrument(skip_all, fields(request_id = request.id, commit))]
+    pub async fn trigger_build(
+        &self,
+        request: VerificationRequest,
+    ) -> Result<String, PipelineError> {
+        self.funds.ensure_can_resolve().await?;
+
+        let repository_config = self.repository.clone();
+        let (id, repository, checkout, path) = (
+            request.id,
+            request.repository.clone(),
+            request.checkout.clone(),
+            request.path.clone(),
+        );
+        let span = Span::current();
+        // A new build may fetch the credential again
+        let ci_token = self.credentials.as_ref().map(|credentials| {
+            credentials.allow_fetch(id);
+            credentials.ci_token(id)
+        });
+        let commit = tokio::task::spawn_blocking(move || {
+            span.in_scope(|| {
+                repository::update(
+                    &repository_config,
+                    id,
+                    &repository,
+                    &checkout,
+                    &path,
+                    ci_token.as_deref(),
+                )
+            })
+        })
+        .await??
+        .to_string();
+        Span::current().record("commit", commit.as_str());
+
+        self.builds.start(commit.clone(), request.clone());
+        self.record(Event::new(
+            &request,
+            EventKind::BuildStarted {
+                commit: commit.clone(),
+            },
+        ));
+
+        Ok(commit)
+    }
+
+    /// Triggers a build for every new pending request, until `shutdown`
+    /// changes. A build that is being triggered is allowed to finish.
+    ///
+    /// Requests that could not be built for lack of funds, or that wait for a
+    /// repository credential, are retried every `interval`, oldest first.
+    /// Requests whose build could not be triggered for other reasons are
+    /// retried with exponential backoff from `interval`.
+    pub async fn watch(&self, interval: Duration, mut shutdown: Receiver<bool>) {
+        let mut requests = watch::list::<VerificationRequest, u64>(
+            self.registry.rpc().clone(),
+            self.registry.contract_id().clone(),
+            "get_pending_requests".to_string(),
+            serde_json::json!({}),
+            interval,
+        );
+
+        let mut retry = tokio::time::interval(interval);
+        let mut deferred = VecDeque::new();
+
+        loop {
+            let (request, failures, is_new) = tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = retry.tick(), if !deferred.is_empty() => match take_due(&mut deferred, unix_now()) {
+                    Some(Deferred { request, failures, .. }) => (request, failures, false),
+                    None => continue,
+                },
+                request = requests.recv() => match request {
+                    Some(request) => (request, 0, true),
+                    None => break,
+                },
+            };
+
+            // Built before a restart
+            if self.builds.contains_request(request.id) {
+                continue;
+            }
+
+            let span = info_span!("request", request_id = request.id);
+
+            async {
+                if is_new {
+                    info!(repository = %request.repository, "New request");
+                    self.record(Event::new(
+                        &request,
+                        EventKind::RequestCreated {
+                            request: request.clone(),
+                        },
+                    ));
+                }
+
+                if self.awaits_credential(&request) {
+                    debug!("Waiting for a repository credential");
+                    deferred.push_back(Deferred {
+                        request,
+                        failures,
+                        retry_at: 0,
+                    });
+                    retry.reset();
+                    return;
+                }
+
+                match self.trigger_build(request.clone()).await {
+                    Ok(_) => info!("Triggered build"),
+                    Err(PipelineError::Funds(e)) => {
+                        warn!("Deferring build: {e}");
+                        deferred.push_back(Deferred {
+                            request,
+                            failures,
+                            retry_at: 0,
+                        });
+                        // Wait a full interval before retrying
+                        retry.reset();
+                    }
+                    Err(e) => {
+                        let failures = failures + 1;
+                        let backoff = backoff(interval, failures);
+                        warn!(
+                            failures,
+                            ?backoff,
+                            "Could not trigger build, will retry: {e}"
+                        );
+                        deferred.push_back(Deferred {
+                            request,
+                            failures,
+                            retry_at: unix_now() + backoff.as_secs(),
+                        });
+                    }
+                }
+            }
+            .instrument(span)
+            .await;
+        }
+
+        info!("Stopped watching for requests");
+    }
+
+    /// Resolves the request that the completed job was building. Returns a
+    /// short description of the outcome.
+    pub async fn job_completed(
+        &self,
+        payload: JobCompletedWebhookPayload,
+    ) -> Result<String, PipelineError> {
+        let job = payload.job;
+
+        if let Some(job_name) = &self.job_name {
+            if &job.name != job_name {
+                return Ok(format!("Ignored job {}", job.name));
+            }
+        }
+
+        // e.g. canceled: the job may be rerun
+        if job.status != "success" && job.status != "failed" {
+            return Ok(format!("Ignored job with status {}", job.status));
+        }
+
+        let commit = payload.pipeline.vcs.revision;
+        let completed_job = CompletedJob {
+            number: job.number,
+            status: job.status,
+        };
+        // Checkpoint, so that resolution can be resumed after a restart
+        match self.builds.complete(&commit, completed_job) {
+            Some(build) => self.attempt_resolution(build).await,
+            None => Ok(format!("No request is being built by commit {commit}")),
+        }
+    }
+
+    /// Resolves builds that are due to be retried every `interval`, until
+    /// `shutdown` changes. This includes builds whose job completed before
+    /// the service last stopped.
+    pub async fn retry_resolutions(&self, interval: Duration, mut shutdown: Receiver<bool>) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            tokio::select! {
+                _ = shutdown.changed() => break,
+                _ = interval.tick() => {}
+            }
+
+            for build in self.builds.unresolved(unix_now()) {
+                let id = build.request.id;
+                if let Ok(outcome) = self.attempt_resolution(build).await {
+                    info!(request_id = id, %outcome, "Resolved on retry");
+                }
+            }
+        }
+    }
+
+    /// Retries resolving a request from the dead letters.
+    pub async fn replay(&self, request_id: u64) -> Result<String, PipelineError> {
+        let build = self
+            .builds
+            .replay(request_id)
+            .ok_or(PipelineError::NotDeadLettered(request_id))?;
+
+        self.attempt_resolution(build).await
+    }
+
+    /// Fetches a request that is still pending.
+    async fn pending_request(&self, id: u64) -> Result<VerificationRequest, PipelineError> {
+        let request = self
+            .registry
+            .get_verification_request(id)
+            .await
+            .map_err(|e| PipelineError::Registry(e.to_string()))?
+            .ok_or(PipelineError::UnknownRequest(id))?;
+
+        if request.status != VerificationStatus::PENDING {
+            return Err(PipelineError::NotPending {
+                id,
+                status: request.status,
+            });
+        }
+
+        Ok(request)
+    }
+
+    /// Builds a pending request again, e.g. after its job was lost. Builds
+    /// already tracked for the request are forgotten, so that their jobs no
+    /// longer resolve it. Returns the hash of the new commit.
+    pub async fn rebuild(&self, request_id: u64) -> Result<String, PipelineError> {
+        let request = self.pending_request(request_id).await?;
+        self.builds.forget_request(request_id);
+
+        self.trigger_build(request).await
+    }
+
+    /// Whether `request` is new enough that its requester may still register
+    /// a credential, which its build needs if the repository is private.
+    fn awaits_credential(&self, request: &VerificationRequest) -> bool {
+        let Some(credentials) = &self.credentials else {
+            return false;
+        };
+
+        !credentials.contains(request.id)
+            && within_wait(request.created_at, self.credential_wait, unix_now())
+    }
+
+    /// Starts the build of a request over once a credential is registered
+    /// for it, unless its build succeeded without one. Returns the commit of
+    /// the new build. Requests that were not built yet are left to
+    /// [`Pipeline::watch`], which stops waiting for their credential.
+    pub async fn credential_registered(
+        &self,
+        request_id: u64,
+    ) -> Result<Option<String>, PipelineError> {
+        if !needs_rebuild(&self.builds.for_request(request_id)) {
+            return Ok(None);
+        }
+
+        info!(request_id, "Rebuilding with the registered credential");
+        self.rebuild(request_id).await.map(Some)
+    }
+
+    /// Resolves a pending request regardless of its builds, which are
+    /// forgotten. Returns a short description of the outcome.
+    #[tracing::instrument(skip_all, fields(request_id, code_hash))]
+    pub async fn resolve_manually(
+        &self,
+        request_id: u64,
+        resolution: ManualResolution,
+    ) -> Result<String, PipelineError> {
+        let request = self.pending_request(request_id).await?;
+
+        let resolution = match resolution {
+            ManualResolution::Success(Artifacts::Job(job_number)) => {
+                Resolution::Verified(self.download_artifacts(job_number).await?)
+            }
+            ManualResolution::Success(Artifacts::Given(meta)) => Resolution::Verified(meta),
+            ManualResolution::Failure => Resolution::Failed(None),
+        };
+        info!("Resolving manually");
+
+        let outcome = self.submit(&request, resolution, None, None).await?;
+        self.builds.forget_request(request_id);
+        self.clean_up_request(request_id).await;
+
+        Ok(outcome)
+    }
+
+    /// Forgets what was only needed to build a request that is resolved: its
+    /// repository credential and its build branch. Failures to delete the
+    /// branch are only logged, since [`Pipeline::clean_up_branches`] catches
+    /// up later.
+    async fn clean_up_request(&self, request_id: u64) {
+        if let Some(credentials) = &self.credentials {
+            credentials.remove(request_id);
+        }
+
+        let config = self.repository.clone();
+        let span = Span::current();
+        let result = async {
+            tokio::task::spawn_blocking(move || {
+                span.in_scope(|| repository::delete_branch(&config, request_id))
+            })
+            .await??;
+            Ok::<_, PipelineError>(())
+        };
+
+        if let Err(e) = result.await {
+            warn!(request_id, "Could not delete build branch: {e}");
+        }
+    }
+
+    /// Deletes the build branches of requests that are no longer tracked,
+    /// e.g. because the service stopped before deleting them.
+    pub async fn clean_up_branches(&self) -> Result<(), PipelineError> {
+        let config = self.repository.clone();
+        let builds = self.builds.clone();
+        let deleted = tokio::task::spawn_blocking(move || {
+            repository::clean_up_branches(&config, |id| builds.contains_request(id))
+        })
+        .await??;
+
+        if !deleted.is_empty() {
+            info!(?deleted, "Deleted stale build branches");
+        }
+
+        Ok(())
+    }
+
+    /// Traces a request to the CI jobs and transactions that handled it.
+    pub async fn correlation(&self, request_id: u64) -> Result<Correlation, PipelineError> {
+        Correlation::collect(&self.registry, &self.builds, &self.journal, request_id).await
+    }
+
+    /// Resolves the build, and schedules a retry (or gives up) if that
+    /// fails.
+    async fn attempt_resolution(&self, build: Build) -> Result<String, PipelineError> {
+        let id = build.request.id;
+        let commit = build.commit.clone();
+        let attempts = build.attempts + 1;
+
+        let e = match self.resolve(build).await {
+            Ok(outcome) => return Ok(outcome),
+            Err(e) => e,
+        };
+
+        if e.is_transient() && attempts < self.resolution_attempts {
+            let backoff = backoff(self.resolution_backoff, attempts);
+            warn!(
+                request_id = id,
+                attempts,
+                ?backoff,
+                "Resolution failed, will retry: {e}"
+            );
+            self.builds
+                .fail(&commit, e.to_string(), Some(unix_now() + backoff.as_secs()));
+        } else {
+            error!(
+                request_id = id,
+                attempts, "Resolution failed, moved to dead letters: {e}"
+            );
+            self.builds.fail(&commit, e.to_string(), None);
+        }
+
+        Err(e)
+    }
+
+    /// Outcome of a resolution transaction: its hash, or `None` if the
+    /// request turns out to be resolved already, which counts as a success.
+    fn resolution_result(
+        id: u64,
+        result: Result<CryptoHash, ContractInteractionError>,
+    ) -> Result<Option<CryptoHash>, PipelineError> {
+        match result {
+            Ok(transaction_hash) => Ok(Some(transaction_hash)),
+            Err(e) if e.is_contract_panic(ALREADY_RESOLVED) => {
+                info!(request_id = id, "Request was already resolved");
+                Ok(None)
+            }
+            Err(e) => Err(PipelineError::resolution(id, e)),
+        }
+    }
+
+    async fn download_artifacts(
+        &self,
+        job_number: u64,
+    ) -> Result<VerificationMetadata, PipelineError> {
+        let meta = request_job(
+            &self.circleci,
+            self.project_slug.clone(),
+            job_number.to_string(),
+        )
+        .await?;
+        self.metrics.artifacts_downloaded();
+        Span::current().record("code_hash", tracing::field::display(&meta.code_hash));
+        info!(code_url = %meta.code_url, "Downloaded build artifacts");
+
+        Ok(meta)
+    }
+
+    /// Fetches the output of a job and stores it, with the repository
+    /// credential of the request redacted. Failures are only logged, since
+    /// the request is resolved either way.
+    async fn capture_log(&self, request_id: u64, job_number: u64) -> Option<BuildLog> {
+        let log = match get_job_log(&self.circleci, &self.project_slug, job_number).await {
+            Ok(log) => log,
+            Err(e) => {
+                warn!(job_number, "Could not fetch build log: {e}");
+                return None;
+            }
+        };
+
+        let credential = self.credentials.as_ref().and_then(|c| c.get(request_id));
+        let secrets = credential.map(|c| c.redactions()).unwrap_or_default();
+        let secrets: Vec<&str> = secrets.iter().map(String::as_str).collect();
+        match self.logs.put(&logs::sanitize(&log, &secrets)) {
+            Ok(log) => {
+                info!(log_hash = %log.hash, "Stored build log");
+                Some(log)
+            }
+            Err(e) => {
+                warn!(job_number, "Could not store build log: {e}");
+                None
+            }
+        }
+    }
+
+    /// Sends the resolution transaction. Returns its hash.
+    async fn send(
+        &self,
+        request: &VerificationRequest,
+        resolution: Resolution,
+    ) -> Result<CryptoHash, ContractInteractionError> {
+        match resolution {
+            Resolution::Verified(meta) => {
+                self.registry
+                    .verification_success(&meta.into_verification(request.id))
+                    .await
+            }
+            Resolution::Failed(log) => {
+                self.registry
+                    .verification_failure(request.id, log.as_ref())
+                    .await
+            }
+        }
+    }
+
+    /// Sends the resolution transaction, unless `unconfirmed`, an earlier one
+    /// for the build triggered by `commit`, may still land. A transaction
+    /// that times out is recorded as unconfirmed. Returns a short description
+    /// of the outcome.
+    async fn submit(
+        &self,
+        request: &VerificationRequest,
+        resolution: Resolution,
+        commit: Option<&str>,
+        unconfirmed: Option<SentTransaction>,
+    ) -> Result<String, PipelineError> {
+        let code_hash = match &resolution {
+            Resolution::Verified(meta) => Some(meta.code_hash.clone()),
+            Resolution::Failed(_) => None,
+        };
+
+        let result = match unconfirmed {
+            Some(transaction) => match self.registry.transaction_outcome(&transaction).await {
+                // Dropped, so another can be sent without resolving twice
+                Err(ContractInteractionError::Expired { .. }) => {
+                    info!(transaction_hash = %transaction.hash, "Earlier resolution expired");
+                    self.send(request, resolution).await
+                }
+                // Still unknown, e.g. timed out again
+                Err(e) if e.is_transient() => {
+                    return Err(PipelineError::resolution(request.id, e));
+                }
+                result => result,
+            },
+            None => self.send(request, resolution).await,
+        };
+        if let Some(commit) = commit {
+            let unconfirmed = match &result {
+                Err(ContractInteractionError::Timeout { transaction, .. }) => {
+                    Some(transaction.clone())
+                }
+                _ => None,
+            };
+            self.builds.set_unconfirmed(commit, unconfirmed);
+        }
+        let transaction_hash = Self::resolution_result(request.id, result)?;
+
+        self.record(Event::new(
+            request,
+            EventKind::Resolved {
+                verified: code_hash.is_some(),
+                code_hash: code_hash.clone(),
+                transaction_hash,
+            },
+        ));
+
+        Ok(match code_hash {
+            Some(code_hash) => format!("{code_hash}"),
+            None => format!("Request {} failed", request.id),
+        })
+    }
+
+    #[tracing::instrument(skip_all, fields(request_id = build.request.id, code_hash))]
+    async fn resolve(&self, build: Build) -> Result<String, PipelineError> {
+        let request = build.request;
+        let job = build
+            .completed_job
+            .expect("Only completed builds are resolved");
+
+        // The resolution transaction may have completed during a shutdown
+        let current = self
+            .registry
+            .get_verification_request(request.id)
+            .await
+            .map_err(|e| PipelineError::resolution(request.id, e))?;
+        if let Some(current) = current.filter(|r| r.status != VerificationStatus::PENDING) {
+            self.builds.finish(&build.commit);
+            self.clean_up_request(request.id).await;
+            return Ok(format!(
+                "Request {} was already resolved ({:?})",
+                request.id, current.status
+            ));
+        }
+
+        let log = match build.log {
+            Some(log) => Some(log),
+            None => {
+                let log = self.capture_log(request.id, job.number).await;
+                if let Some(log) = &log {
+                    self.builds.set_log(&build.commit, log.clone());
+                }
+                log
+            }
+        };
+        let log_hash = log.as_ref().map(|log| log.hash.clone());
+        let resolution = if job.status == "success" {
+            let meta = self.download_artifacts(job.number).await?;
+            self.record(Event::new(
+                &request,
+                EventKind::BuildFinished {
+                    job_number: job.number,
+                    status: job.status,
+                    code_hash: Some(meta.code_hash.clone()),
+                    log_hash,
+                },
+            ));
+            Resolution::Verified(meta)
+        } else {
+            self.record(Event::new(
+                &request,
+                EventKind::BuildFinished {
+                    job_number: job.number,
+                    status: job.status,
+                    code_hash: None,
+                    log_hash,
+                },
+            ));
+            Resolution::Failed(log)
+        };
+
+        let outcome = self
+            .submit(&request, resolution, Some(&build.commit), build.unconfirmed)
+            .await?;
+        self.builds.finish(&build.commit);
+        self.clean_up_request(request.id).await;
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use std::{collections::VecDeque, time::Duration};
+
+    use crate::events::test_request;
+
+    use super::{
+        backoff,
+        builds::{Build, CompletedJob},
+        needs_rebuild, take_due, within_wait, Artifacts, Deferred, ManualResolution, MAX_BACKOFF,
+    };
+
+    #[test]
+    fn retries_failed_triggers_when_due() {
+        let interval = Duration::from_secs(10);
+        assert_eq!(backoff(interval, 1), interval);
+        assert_eq!(backoff(interval, 3), Duration::from_secs(40));
+        assert_eq!(backoff(interval, 100), MAX_BACKOFF);
+
+        let deferred = |id, retry_at| Deferred {
+            request: test_request(id, "alice.near"),
+            failures: 1,
+            retry_at,
+        };
+        let mut queue = VecDeque::from([deferred(1, 1_010), deferred(2, 0)]);
+
+        // The failed request waits out its backoff behind others
+        assert_eq!(take_due(&mut queue, 1_000).unwrap().request.id, 2);
+        assert!(take_due(&mut queue, 1_000).is_none());
+        assert_eq!(take_due(&mut queue, 1_010).unwrap().request.id, 1);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn waits_for_credential_after_creation() {
+        let created_at = 1_000 * 1_000_000_000;
+        let wait = Duration::from_secs(120);
+
+        assert!(within_wait(created_at, wait, 1_000));
+        assert!(within_wait(created_at, wait, 1_119));
+        assert!(!within_wait(created_at, wait, 1_120));
+        assert!(!within_wait(created_at, Duration::ZERO, 1_000));
+    }
+
+    #[test]
+    fn rebuilds_unless_a_build_succeeded() {
+        let build = |status: Option<&str>| Build {
+            request: test_request(7, "alice.near"),
+            commit: "abc".to_string(),
+            completed_job: status.map(|status| CompletedJob {
+                number: 12,
+                status: status.to_string(),
+            }),
+            log: None,
+            unconfirmed: None,
+            attempts: 0,
+            retry_at: 0,
+            last_error: None,
+            dead_letter: false,
+        };
+
+        assert!(!needs_rebuild(&[]));
+        assert!(needs_rebuild(&[build(None)]));
+        assert!(needs_rebuild(&[build(Some("failed"))]));
+        assert!(!needs_rebuild(&[
+            build(Some("failed")),
+            build(Some("success"))
+        ]));
+    }
+
+    #[test]
+    fn parses_manual_resolution() {
+        let parse = |value| serde_json::from_value::<ManualResolution>(value).unwrap();
+
+        assert!(matches!(
+            parse(json!({ "status": "success", "job": 12 })),
+            ManualResolution::Success(Artifacts::Job(12))
+        ));
+        assert!(matches!(
+            parse(json!({
+                "status": "success",
+                "artifacts": {
+                    "repo": "https://github.com/example/contract.git",
+                    "remote": "origin",
+                    "branch": "main",
+                    "commit": "abc",
+                    "code_url": "https://example.com/contract.wasm",
+                    "code_hash": "11111111111111111111111111111111",
+                },
+            })),
+            ManualResolution::Success(Artifacts::Given(_))
+        ));
+        assert!(matches!(
+            parse(json!({ "status": "failure" })),
+            ManualResolution::Failure
+        ));
+        assert!(
+            serde_json::from_value::<ManualResolution>(json!({ "status": "success" })).is_err()
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::convert::Infallible;
+
+use serde::Serialize;
+use tracing::error;
+use warp::{http::StatusCode, reject, Rejection, Reply};
+
+use crate::{
+    admin::Unauthorized,
+    api::ApiError,
+    circleci::{
+        client::ParallelError,
+        error::CircleCiError,
+        signature::{IncompatibleSignatureVersion, InvalidSignature, MalformedSignature},
+        webhook::WebhookError,
+    },
+    pipeline::PipelineError,
+    repository::access::AccessError,
+};
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    code: u16,
+    message: String,
+}
+
+fn classify(err: &Rejection) -> (StatusCode, String) {
+    if err.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if err.find::<Unauthorized>().is_some() {
+        (
+            StatusCode::UNAUTHORIZED,
+            "Invalid or missing bearer token".to_string(),
+        )
+    } else if err.find::<InvalidSignature>().is_some() {
+        (StatusCode::UNAUTHORIZED, "Invalid signature".to_string())
+    } else if err.find::<IncompatibleSignatureVersion>().is_some() {
+        (
+            StatusCode::BAD_REQUEST,
+            "Incompatible signature version".to_string(),
+        )
+    } else if let Some(e) = err.find::<MalformedSignature>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<WebhookError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<CircleCiError>() {
+        (StatusCode::BAD_GATEWAY, e.to_string())
+    } else if let Some(e) = err.find::<PipelineError>() {
+        match e {
+            PipelineError::CircleCi(ParallelError::TaskError(_))
+            | PipelineError::Resolution { .. }
+            | PipelineError::Registry(_) => (StatusCode::BAD_GATEWAY, e.to_string()),
+            PipelineError::CircleCi(ParallelError::JoinError(_))
+            | PipelineError::Repository(_)
+            | PipelineError::Join(_)
+            | PipelineError::State(_) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            PipelineError::Funds(_) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+            PipelineError::NotDeadLettered(_) | PipelineError::UnknownRequest(_) => {
+                (StatusCode::NOT_FOUND, e.to_string())
+            }
+            PipelineError::NotPending { .. } => (StatusCode::CONFLICT, e.to_string()),
+        }
+    } else if let Some(e) = err.find::<AccessError>() {
+        match e {
+            AccessError::UnknownRequest(_)
+            | AccessError::NoCredential(_)
+            | AccessError::Disabled => (StatusCode::NOT_FOUND, e.to_string()),
+            AccessError::NotPending(_) => (StatusCode::CONFLICT, e.to_string()),
+            AccessError::AlreadyFetched(_) => (StatusCode::GONE, e.to_string()),
+            AccessError::InvalidSignature => (StatusCode::UNAUTHORIZED, e.to_string()),
+            AccessError::KeyNotAuthorized { .. } | AccessError::UnknownRequester(_) => {
+                (StatusCode::FORBIDDEN, e.to_string())
+            }
+            AccessError::Registry(_) => (StatusCode::BAD_GATEWAY, e.to_string()),
+        }
+    } else if let Some(e) = err.find::<ApiError>() {
+        match e {
+            ApiError::NotFound(_) => (StatusCode::NOT_FOUND, e.to_string()),
+            ApiError::InvalidParameter(_) => (StatusCode::BAD_REQUEST, e.to_string()),
+            ApiError::Upstream(_) => (StatusCode::BAD_GATEWAY, e.to_string()),
+        }
+    } else if let Some(e) = err.find::<reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<reject::InvalidHeader>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<reject::UnsupportedMediaType>() {
+        (StatusCode::UNSUPPORTED_MEDIA_TYPE, e.to_string())
+    } else if let Some(e) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<reject::MissingHeader>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = err.find::<reject::PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = err.find::<reject::LengthRequired>() {
+        (StatusCode::LENGTH_REQUIRED, e.to_string())
+    } else if let Some(e) = err.find::<reject::MethodNotAllowed>() {
+        (StatusCode::METHOD_NOT_ALLOWED, e.to_string())
+    } else {
+        error!("Unhandled rejection: {err:?}");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error".to_string(),
+        )
+    }
+}
+
+/// Converts rejections from any route into a JSON error body with an
+/// appropriate status code.
+pub async fn recover(err: Rejection) -> Result<impl Reply, Infallible> {
+    let (status, message) = classify(&err);
+
+    let json = warp::reply::json(&ErrorResponse {
+        code: status.as_u16(),
+        message,
+    });
+
+    Ok(warp::reply::with_status(json, status))
+}
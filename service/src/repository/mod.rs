@@ -1,71 +1,360 @@
 // https://siciarz.net/24-days-rust-git2/
 
-use git2::{Direction, Repository, Signature};
-use std::path::Path;
+use git2::{
+    Commit, Cred, CredentialType, Direction, ErrorClass, ErrorCode, FetchOptions, FileMode, Oid,
+    PushOptions, RemoteCallbacks, Repository, Signature,
+};
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+};
+use tracing::{debug, info, warn};
 
-fn push(repo: &Repository) -> Result<(), git2::Error> {
-    let mut remote = repo.find_remote("origin").expect("Repository does not have remote 'origin' configured.");
-    remote.connect(Direction::Push)?;
-    remote.push(&["refs/heads/main:refs/heads/main"], None)
+use crate::signer::credentials::expand_home;
+
+pub mod access;
+
+/// Each request is built on its own branch, so that requests arriving
+/// together do not overwrite each other before CI checks them out.
+const REQUEST_BRANCH_PREFIX: &str = "verify/";
+
+/// Number of times a rejected push is rebased onto the remote branch and
+/// retried.
+const MAX_PUSH_ATTEMPTS: usize = 3;
+
+/// libgit2 asks again after rejected credentials, so give up eventually.
+const MAX_CREDENTIAL_ATTEMPTS: usize = 3;
+
+/// Username for token authentication if none is configured. GitHub accepts
+/// any username alongside a token.
+const DEFAULT_TOKEN_USERNAME: &str = "x-access-token";
+
+#[derive(Debug, Clone, Default)]
+pub struct GitCredentials {
+    /// Private key for SSH remotes. The SSH agent is used if unset.
+    pub ssh_key_path: Option<PathBuf>,
+    pub ssh_key_passphrase: Option<String>,
+    /// Username for HTTPS remotes
+    pub username: Option<String>,
+    /// Password or access token for HTTPS remotes
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepositoryConfig {
+    /// Local clone of the CI repository
+    pub path: PathBuf,
+    pub remote: String,
+    /// Branch that CI builds
+    pub branch: String,
+    pub credentials: GitCredentials,
+}
+
+impl RepositoryConfig {
+    fn remote_ref(&self, branch: &str) -> String {
+        format!("refs/remotes/{}/{branch}", self.remote)
+    }
+}
+
+/// Branch that builds `request_id`.
+pub fn request_branch(request_id: u64) -> String {
+    format!("{REQUEST_BRANCH_PREFIX}{request_id}")
+}
+
+/// Supplies credentials from `credentials`, trying each kind the remote
+/// accepts.
+fn callbacks(credentials: &GitCredentials) -> RemoteCallbacks<'_> {
+    let attempts = Cell::new(0);
+    let mut callbacks = RemoteCallbacks::new();
+
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        attempts.set(attempts.get() + 1);
+        if attempts.get() > MAX_CREDENTIAL_ATTEMPTS {
+            return Err(git2::Error::from_str(&format!(
+                "Authentication to {url} failed"
+            )));
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            let username = username_from_url.unwrap_or("git");
+            match &credentials.ssh_key_path {
+                Some(key) => Cred::ssh_key(
+                    username,
+                    None,
+                    &expand_home(key),
+                    credentials.ssh_key_passphrase.as_deref(),
+                ),
+                None => Cred::ssh_key_from_agent(username),
+            }
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            match &credentials.token {
+                Some(token) => Cred::userpass_plaintext(
+                    credentials
+                        .username
+                        .as_deref()
+                        .or(username_from_url)
+                        .unwrap_or(DEFAULT_TOKEN_USERNAME),
+                    token,
+                ),
+                None => Err(git2::Error::from_str(&format!(
+                    "{url} requires a username and token"
+                ))),
+            }
+        } else if allowed.contains(CredentialType::USERNAME) {
+            Cred::username(username_from_url.unwrap_or("git"))
+        } else {
+            Cred::default()
+        }
+    });
+
+    callbacks
+}
+
+fn is_rejected(e: &git2::Error) -> bool {
+    e.code() == ErrorCode::NotFastForward
 }
 
+/// Pushes `refspecs`. Fails with [`ErrorCode::NotFastForward`] if the
+/// remote has commits that the local branch does not.
+fn push(
+    repo: &Repository,
+    config: &RepositoryConfig,
+    refspecs: &[String],
+) -> Result<(), git2::Error> {
+    let mut remote = repo.find_remote(&config.remote)?;
+    let rejection = RefCell::new(None);
+
+    let result = {
+        let mut callbacks = callbacks(&config.credentials);
+        callbacks.push_update_reference(|refname, status| {
+            if let Some(status) = status {
+                *rejection.borrow_mut() = Some(format!("{refname}: {status}"));
+            }
+            Ok(())
+        });
+
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        remote.push(refspecs, Some(&mut options))
+    };
+
+    result.map_err(|e| match e.code() {
+        // Local remotes report the rejection as an error
+        ErrorCode::NotFastForward => e,
+        _ if e.message().contains("non-fast-forward")
+            || e.message().contains("not fast-forward") =>
+        {
+            git2::Error::new(ErrorCode::NotFastForward, e.class(), e.message())
+        }
+        _ => e,
+    })?;
+
+    match rejection.into_inner() {
+        Some(message) => Err(git2::Error::new(
+            ErrorCode::NotFastForward,
+            ErrorClass::Reference,
+            format!("Push was rejected ({message})"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Fetches `branch` from the remote. Returns its tip.
+fn fetch<'r>(
+    repo: &'r Repository,
+    config: &RepositoryConfig,
+    branch: &str,
+) -> Result<Commit<'r>, git2::Error> {
+    let mut remote = repo.find_remote(&config.remote)?;
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks(&config.credentials));
+
+    let remote_ref = config.remote_ref(branch);
+    remote.fetch(
+        &[format!("+refs/heads/{branch}:{remote_ref}")],
+        Some(&mut options),
+        None,
+    )?;
+
+    repo.find_reference(&remote_ref)?.peel_to_commit()
+}
+
+/// Commits `files` on top of `parent`, and points `branch` at the commit.
+/// The tree is built from `parent` rather than the working directory, so
+/// that the same change can be replayed onto a different parent.
+fn commit_files(
+    repo: &Repository,
+    branch: &str,
+    parent: &Commit,
+    files: &[(&str, &str)],
+    message: &str,
+) -> Result<Oid, git2::Error> {
+    let mut tree = repo.treebuilder(Some(&parent.tree()?))?;
+    for (name, contents) in files {
+        tree.insert(name, repo.blob(contents.as_bytes())?, FileMode::Blob.into())?;
+    }
+    let tree = repo.find_tree(tree.write()?)?;
+
+    let signature = Signature::now("Contract Registry Bot", "contract-registry@stats.gallery")?;
+    let commit = repo.commit(None, &signature, &signature, message, &tree, &[parent])?;
+
+    // Replaces the previous attempt when rebasing
+    repo.reference(
+        &format!("refs/heads/{branch}"),
+        commit,
+        true,
+        "Automated update",
+    )?;
+
+    Ok(commit)
+}
+
+/// Commits the request to its own branch, based on the configured branch,
+/// and pushes it. Returns the commit.
+///
+/// If the request was built before, the push is rejected and the commit is
+/// rebased onto the earlier build.
+#[tracing::instrument(skip(config), fields(remote = %config.remote, branch))]
 pub fn update(
-    repo_path: &Path,
+    config: &RepositoryConfig,
+    request_id: u64,
     target_repo: &str,
     checkout: &str,
     path: &str,
-) -> Result<(), git2::Error> {
-    let repo_handle = Repository::open(repo_path)?;
-    let mut index = repo_handle.index()?;
+    ci_token: Option<&str>,
+) -> Result<Oid, git2::Error> {
+    let repo_handle = Repository::open(&config.path)?;
+    let branch = request_branch(request_id);
+    tracing::Span::current().record("branch", branch.as_str());
 
-    let pairs = vec![
+    let request_id_text = request_id.to_string();
+    let mut files = vec![
+        ("request_id.txt", request_id_text.as_str()),
         ("repository.txt", target_repo),
         ("checkout.txt", checkout),
         ("path.txt", path),
     ];
+    // Lets CI fetch the credential of a private repository
+    if let Some(ci_token) = ci_token {
+        files.push(("ci_token.txt", ci_token));
+    }
+    let message = format!(
+        "Automated update\n\tTarget repository: {target_repo}\n\tCheckout: {checkout}\n\tPath: {path}"
+    );
 
-    println!("Writing output to repository...");
+    debug!("Writing output to repository");
 
-    for (path, value) in pairs {
-        std::fs::write(repo_path.join(path), value).unwrap();
-        index.add_path(Path::new(path))?;
-    }
+    // The latest CI configuration lives on the configured branch
+    let mut parent = fetch(&repo_handle, config, &config.branch)?;
 
-    println!("Done writing output.");
+    let refspec = format!("refs/heads/{branch}:refs/heads/{branch}");
+    let mut attempt = 1;
+    loop {
+        let commit_oid = commit_files(&repo_handle, &branch, &parent, &files, &message)?;
+        info!(commit = %commit_oid, attempt, "Committed, pushing");
 
-    let oid = index.write_tree()?;
+        match push(&repo_handle, config, std::slice::from_ref(&refspec)) {
+            Ok(()) => {
+                debug!("Pushed");
+                return Ok(commit_oid);
+            }
+            Err(e) if is_rejected(&e) && attempt < MAX_PUSH_ATTEMPTS => {
+                warn!("{e}; rebasing onto {}", config.remote_ref(&branch));
+                parent = fetch(&repo_handle, config, &branch)?;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
 
-    let signature = Signature::now("Contract Registry Bot", "contract-registry@stats.gallery")?;
+/// Deletes the branches of the given requests, locally and on the remote.
+fn delete_branches(
+    repo: &Repository,
+    config: &RepositoryConfig,
+    request_ids: &[u64],
+) -> Result<(), git2::Error> {
+    if request_ids.is_empty() {
+        return Ok(());
+    }
 
-    let parent_commit = repo_handle.head()?.resolve()?.peel_to_commit()?;
+    let branches = request_ids
+        .iter()
+        .map(|id| request_branch(*id))
+        .collect::<Vec<_>>();
+    let refspecs = branches
+        .iter()
+        .map(|branch| format!(":refs/heads/{branch}"))
+        .collect::<Vec<_>>();
+    push(repo, config, &refspecs)?;
 
-    let tree = repo_handle.find_tree(oid)?;
+    for branch in &branches {
+        for name in [format!("refs/heads/{branch}"), config.remote_ref(branch)] {
+            match repo.find_reference(&name) {
+                Ok(mut reference) => reference.delete()?,
+                Err(e) if e.code() == ErrorCode::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
 
-    let commit_oid = repo_handle.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        &format!("Automated update\n\tTarget repository: {target_repo}\n\tCheckout: {checkout}\n\tPath: {path}"),
-        &tree,
-        &[&parent_commit],
-    )?;
+    Ok(())
+}
 
-    println!("Commit hash: {commit_oid:?}");
+/// Deletes the branch of a request that no longer needs to be built.
+#[tracing::instrument(skip(config))]
+pub fn delete_branch(config: &RepositoryConfig, request_id: u64) -> Result<(), git2::Error> {
+    let repo_handle = Repository::open(&config.path)?;
+    delete_branches(&repo_handle, config, &[request_id])?;
+    debug!("Deleted branch");
 
-    println!("Pushing...");
+    Ok(())
+}
 
-    push(&repo_handle)?;
+/// Deletes the branches of all requests on the remote for which `keep`
+/// returns false, e.g. those left behind by a crash. Returns the IDs of
+/// those requests.
+#[tracing::instrument(skip_all)]
+pub fn clean_up_branches(
+    config: &RepositoryConfig,
+    keep: impl Fn(u64) -> bool,
+) -> Result<Vec<u64>, git2::Error> {
+    let repo_handle = Repository::open(&config.path)?;
 
-    println!("Done.");
+    let stale = {
+        let mut remote = repo_handle.find_remote(&config.remote)?;
+        let connection =
+            remote.connect_auth(Direction::Fetch, Some(callbacks(&config.credentials)), None)?;
+        let prefix = format!("refs/heads/{REQUEST_BRANCH_PREFIX}");
+        connection
+            .list()?
+            .iter()
+            .filter_map(|head| head.name().strip_prefix(&prefix)?.parse().ok())
+            .filter(|id| !keep(*id))
+            .collect::<Vec<u64>>()
+    };
 
-    Ok(())
+    delete_branches(&repo_handle, config, &stale)?;
+
+    Ok(stale)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
+
+    use git2::{Repository, Signature};
 
-    use super::update;
+    use super::{clean_up_branches, delete_branch, update, GitCredentials, RepositoryConfig};
+
+    fn config(path: &Path) -> RepositoryConfig {
+        RepositoryConfig {
+            path: path.to_path_buf(),
+            remote: "origin".to_string(),
+            branch: "main".to_string(),
+            credentials: GitCredentials::default(),
+        }
+    }
 
     #[test]
     fn test() {
@@ -73,11 +362,124 @@ mod tests {
         println!(
             "{:?}",
             update(
-                &Path::new("C:/Users/Jacob/Projects/contract-registry-ci-test"),
+                &config(Path::new(
+                    "C:/Users/Jacob/Projects/contract-registry-ci-test"
+                )),
+                0,
                 "https://github.com/NEAR-Edu/stats.gallery-dapp.git",
                 "main",
                 "",
+                None,
             )
         );
     }
+
+    /// A bare remote with one commit on `main`, and two clones of it.
+    fn setup(name: &str) -> (PathBuf, PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let (remote, a, b) = (dir.join("remote"), dir.join("a"), dir.join("b"));
+
+        let bare = Repository::init_bare(&remote).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree = bare.find_tree(bare.treebuilder(None).unwrap().write().unwrap());
+        bare.commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "Initial commit",
+            &tree.unwrap(),
+            &[],
+        )
+        .unwrap();
+        bare.set_head("refs/heads/main").unwrap();
+
+        let url = remote.to_str().unwrap();
+        Repository::clone(url, &a).unwrap();
+        Repository::clone(url, &b).unwrap();
+
+        (dir, a, b)
+    }
+
+    fn remote_branches(dir: &Path) -> Vec<String> {
+        let remote = Repository::open_bare(dir.join("remote")).unwrap();
+        let mut names = remote
+            .references()
+            .unwrap()
+            .map(|r| r.unwrap().name().unwrap().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn builds_each_request_on_its_own_branch() {
+        let (dir, a, b) = setup("request-branches");
+
+        let first = update(
+            &config(&a),
+            1,
+            "https://example.com/first.git",
+            "main",
+            "",
+            None,
+        )
+        .unwrap();
+        update(
+            &config(&b),
+            2,
+            "https://example.com/second.git",
+            "v1",
+            "",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            remote_branches(&dir),
+            [
+                "refs/heads/main",
+                "refs/heads/verify/1",
+                "refs/heads/verify/2"
+            ]
+        );
+
+        // `b` has not seen the first build of request 1, so its push is
+        // rejected at first
+        let rebuilt = update(
+            &config(&b),
+            1,
+            "https://example.com/first.git",
+            "v2",
+            "",
+            None,
+        )
+        .unwrap();
+        let remote = Repository::open_bare(dir.join("remote")).unwrap();
+        let tip = remote
+            .find_reference("refs/heads/verify/1")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(tip.id(), rebuilt);
+        assert_eq!(tip.parent(0).unwrap().id(), first);
+        // The configured branch is untouched
+        assert_eq!(
+            remote
+                .find_reference("refs/heads/main")
+                .unwrap()
+                .peel_to_commit()
+                .unwrap()
+                .parent_count(),
+            0
+        );
+
+        delete_branch(&config(&a), 2).unwrap();
+        assert_eq!(
+            clean_up_branches(&config(&a), |id| id == 3).unwrap(),
+            vec![1]
+        );
+        assert_eq!(remote_branches(&dir), ["refs/heads/main"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
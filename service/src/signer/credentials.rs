@@ -0,0 +1,50 @@
+//! Key files written by `near login`, laid out as
+//! `<dir>/<network>/<account>.json`.
+
+use std::path::{Path, PathBuf};
+
+use near_crypto::SecretKey;
+use near_primitives::types::AccountId;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct CredentialsFile {
+    account_id: AccountId,
+    // near-cli writes `private_key`, nearcore writes `secret_key`
+    #[serde(alias = "private_key")]
+    secret_key: SecretKey,
+}
+
+/// Expands a leading `~` to the home directory.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub fn path(dir: &Path, network_id: &str, account_id: &AccountId) -> PathBuf {
+    expand_home(dir)
+        .join(network_id)
+        .join(format!("{account_id}.json"))
+}
+
+/// Loads the key of `account_id` from the credentials directory `dir`.
+pub fn load(dir: &Path, network_id: &str, account_id: &AccountId) -> Result<SecretKey, String> {
+    let path = path(dir, network_id, account_id);
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    let file: CredentialsFile = serde_json::from_str(&contents)
+        .map_err(|e| format!("Could not parse {}: {e}", path.display()))?;
+
+    if &file.account_id != account_id {
+        return Err(format!(
+            "{} holds a key for {}, not {account_id}",
+            path.display(),
+            file.account_id
+        ));
+    }
+
+    Ok(file.secret_key)
+}
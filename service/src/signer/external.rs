@@ -0,0 +1,209 @@
+//! Signing with a key held by another process (e.g. a hardware wallet bridge
+//! or a KMS client), reached over a Unix socket.
+//!
+//! Each connection carries one request, a line of JSON:
+//!
+//! ```json
+//! {"account_id": "...", "public_key": "ed25519:...", "hash": "<base58>", "transaction": "<base64>"}
+//! ```
+//!
+//! `transaction` is the Borsh-encoded transaction, so that the signer can
+//! apply its own policy before signing `hash`, its SHA-256 digest. The
+//! signer answers with `{"signature": "ed25519:..."}` or `{"error": "..."}`.
+
+use std::{path::PathBuf, time::Duration};
+
+use near_crypto::{PublicKey, Signature};
+use near_primitives::{
+    borsh::BorshSerialize, hash::CryptoHash, serialize::to_base64, transaction::Transaction,
+    types::AccountId,
+};
+use serde::{Deserialize, Serialize};
+
+use super::SignerError;
+
+/// How long to wait for the signer to answer, including any confirmation it
+/// may ask for.
+const SIGN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    account_id: &'a AccountId,
+    public_key: &'a PublicKey,
+    hash: &'a CryptoHash,
+    transaction: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SignResponse {
+    Signature { signature: Signature },
+    Error { error: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternalSigner {
+    pub account_id: AccountId,
+    pub public_key: PublicKey,
+    socket: PathBuf,
+}
+
+impl ExternalSigner {
+    pub fn new(socket: PathBuf, account_id: AccountId, public_key: PublicKey) -> Self {
+        Self {
+            account_id,
+            public_key,
+            socket,
+        }
+    }
+
+    pub async fn sign(
+        &self,
+        transaction: &Transaction,
+        hash: &CryptoHash,
+    ) -> Result<Signature, SignerError> {
+        let mut request = serde_json::to_vec(&SignRequest {
+            account_id: &self.account_id,
+            public_key: &self.public_key,
+            hash,
+            transaction: to_base64(
+                transaction
+                    .try_to_vec()
+                    .expect("Transactions are always serializable"),
+            ),
+        })
+        .expect("Requests are always serializable");
+        request.push(b'\n');
+
+        let line = tokio::time::timeout(SIGN_TIMEOUT, self.exchange(&request))
+            .await
+            .map_err(|_| SignerError::External(format!("No answer within {SIGN_TIMEOUT:?}")))??;
+
+        let signature = match serde_json::from_str(&line) {
+            Ok(SignResponse::Signature { signature }) => signature,
+            Ok(SignResponse::Error { error }) => return Err(SignerError::External(error)),
+            Err(e) => return Err(SignerError::External(format!("Invalid response: {e}"))),
+        };
+
+        // The transaction would be rejected by the network anyway, but this
+        // points at the actual culprit
+        if !signature.verify(hash.as_ref(), &self.public_key) {
+            return Err(SignerError::InvalidSignature(self.public_key.clone()));
+        }
+
+        Ok(signature)
+    }
+
+    #[cfg(unix)]
+    async fn exchange(&self, request: &[u8]) -> Result<String, SignerError> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let connect_error = |source| SignerError::Connect {
+            path: self.socket.clone(),
+            source,
+        };
+
+        let mut stream = tokio::net::UnixStream::connect(&self.socket)
+            .await
+            .map_err(connect_error)?;
+        stream.write_all(request).await.map_err(connect_error)?;
+
+        let mut line = String::new();
+        BufReader::new(stream)
+            .read_line(&mut line)
+            .await
+            .map_err(connect_error)?;
+
+        Ok(line)
+    }
+
+    #[cfg(not(unix))]
+    async fn exchange(&self, _request: &[u8]) -> Result<String, SignerError> {
+        Err(SignerError::External(
+            "External signers are only supported on Unix".to_string(),
+        ))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use near_crypto::{InMemorySigner, KeyType, Signer};
+    use near_primitives::{hash::CryptoHash, transaction::Transaction};
+    use serde_json::Value;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::UnixListener,
+    };
+
+    use crate::signer::{SignerError, TransactionSigner};
+
+    use super::ExternalSigner;
+
+    /// Serves a single request, signing with `key`.
+    fn serve_once(name: &str, key: InMemorySigner) -> std::path::PathBuf {
+        let socket = std::env::temp_dir().join(format!("{name}-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut line = String::new();
+            BufReader::new(read).read_line(&mut line).await.unwrap();
+
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let hash: CryptoHash = serde_json::from_value(request["hash"].clone()).unwrap();
+            let response = serde_json::json!({ "signature": key.sign(hash.as_ref()) });
+            write
+                .write_all(format!("{response}\n").as_bytes())
+                .await
+                .unwrap();
+        });
+
+        socket
+    }
+
+    fn transaction(signer: &ExternalSigner) -> Transaction {
+        Transaction {
+            signer_id: signer.account_id.clone(),
+            public_key: signer.public_key.clone(),
+            nonce: 1,
+            receiver_id: "registry.test.near".parse().unwrap(),
+            block_hash: CryptoHash::default(),
+            actions: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn signs_over_socket() {
+        let key =
+            InMemorySigner::from_seed("service.test.near".parse().unwrap(), KeyType::ED25519, "a");
+        let other =
+            InMemorySigner::from_seed("service.test.near".parse().unwrap(), KeyType::ED25519, "b");
+
+        let signer = ExternalSigner::new(
+            serve_once("signs-over-socket", key.clone()),
+            key.account_id.clone(),
+            key.public_key.clone(),
+        );
+        let signed = TransactionSigner::External(signer.clone())
+            .sign(transaction(&signer))
+            .await
+            .unwrap();
+        assert!(signed
+            .signature
+            .verify(signed.get_hash().as_ref(), &key.public_key));
+
+        // Signing with a different key than configured is caught
+        let signer = ExternalSigner::new(
+            serve_once("wrong-key", other),
+            key.account_id.clone(),
+            key.public_key.clone(),
+        );
+        let err = TransactionSigner::External(signer.clone())
+            .sign(transaction(&signer))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SignerError::InvalidSignature(_)));
+    }
+}
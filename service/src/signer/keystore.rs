@@ -0,0 +1,182 @@
+//! Passphrase-encrypted files of secret keys.
+//!
+//! The passphrase is stretched with scrypt and the keys are sealed with
+//! XChaCha20-Poly1305, so a wrong passphrase is detected rather than
+//! producing garbage keys.
+
+use std::{io::Write, path::Path};
+
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng},
+    AeadCore, XChaCha20Poly1305, XNonce,
+};
+use near_crypto::SecretKey;
+use near_primitives::serialize::{from_base64, to_base64};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const VERSION: u32 = 1;
+/// scrypt cost (2^15 iterations with r = 8 uses 32 MiB)
+const LOG_N: u8 = 15;
+const R: u32 = 8;
+const P: u32 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Keystore is malformed: {0}")]
+    Format(String),
+    #[error("Wrong passphrase, or the keystore is corrupted")]
+    Decrypt,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Kdf {
+    log_n: u8,
+    r: u32,
+    p: u32,
+    /// Base64
+    salt: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Keystore {
+    version: u32,
+    kdf: Kdf,
+    /// Base64
+    nonce: String,
+    /// Base64
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Contents {
+    secret_keys: Vec<SecretKey>,
+}
+
+fn derive_key(passphrase: &str, kdf: &Kdf) -> Result<chacha20poly1305::Key, KeystoreError> {
+    let salt = from_base64(&kdf.salt).map_err(|e| KeystoreError::Format(e.to_string()))?;
+    let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p, KEY_LEN)
+        .map_err(|e| KeystoreError::Format(e.to_string()))?;
+
+    let mut key = chacha20poly1305::Key::default();
+    scrypt::scrypt(passphrase.as_bytes(), &salt, &params, &mut key)
+        .expect("Key length is valid for scrypt");
+
+    Ok(key)
+}
+
+fn encrypt_with_cost(secret_keys: &[SecretKey], passphrase: &str, log_n: u8) -> String {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = Kdf {
+        log_n,
+        r: R,
+        p: P,
+        salt: to_base64(salt),
+    };
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &kdf).unwrap());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let plaintext = serde_json::to_vec(&Contents {
+        secret_keys: secret_keys.to_vec(),
+    })
+    .unwrap();
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .expect("Encryption does not fail for in-memory buffers");
+
+    serde_json::to_string_pretty(&Keystore {
+        version: VERSION,
+        kdf,
+        nonce: to_base64(nonce),
+        ciphertext: to_base64(ciphertext),
+    })
+    .unwrap()
+}
+
+/// Serializes an encrypted keystore holding `secret_keys`.
+pub fn encrypt(secret_keys: &[SecretKey], passphrase: &str) -> String {
+    encrypt_with_cost(secret_keys, passphrase, LOG_N)
+}
+
+pub fn decrypt(keystore: &str, passphrase: &str) -> Result<Vec<SecretKey>, KeystoreError> {
+    let keystore: Keystore =
+        serde_json::from_str(keystore).map_err(|e| KeystoreError::Format(e.to_string()))?;
+    if keystore.version != VERSION {
+        return Err(KeystoreError::Format(format!(
+            "Unsupported version {}",
+            keystore.version
+        )));
+    }
+
+    let decode = |s: &str| from_base64(s).map_err(|e| KeystoreError::Format(e.to_string()));
+    let nonce = decode(&keystore.nonce)?;
+    if nonce.len() != XNonce::default().len() {
+        return Err(KeystoreError::Format("Invalid nonce length".to_string()));
+    }
+
+    let cipher = XChaCha20Poly1305::new(&derive_key(passphrase, &keystore.kdf)?);
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            decode(&keystore.ciphertext)?.as_slice(),
+        )
+        .map_err(|_| KeystoreError::Decrypt)?;
+    let contents: Contents =
+        serde_json::from_slice(&plaintext).map_err(|e| KeystoreError::Format(e.to_string()))?;
+
+    Ok(contents.secret_keys)
+}
+
+pub fn read(path: &Path, passphrase: &str) -> Result<Vec<SecretKey>, KeystoreError> {
+    decrypt(&std::fs::read_to_string(path)?, passphrase)
+}
+
+/// Creates a keystore at `path`, which must not exist yet. The file is only
+/// readable by its owner.
+pub fn create(
+    path: &Path,
+    secret_keys: &[SecretKey],
+    passphrase: &str,
+) -> Result<(), KeystoreError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options
+        .open(path)?
+        .write_all(encrypt(secret_keys, passphrase).as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use near_crypto::{KeyType, SecretKey};
+
+    use super::{decrypt, encrypt_with_cost, KeystoreError};
+
+    #[test]
+    fn round_trip() {
+        let keys = vec![
+            SecretKey::from_seed(KeyType::ED25519, "a"),
+            SecretKey::from_seed(KeyType::ED25519, "b"),
+        ];
+        // Cheap enough for unoptimized builds
+        let keystore = encrypt_with_cost(&keys, "correct horse", 4);
+
+        assert_eq!(decrypt(&keystore, "correct horse").unwrap(), keys);
+        assert!(matches!(
+            decrypt(&keystore, "battery staple"),
+            Err(KeystoreError::Decrypt)
+        ));
+    }
+}
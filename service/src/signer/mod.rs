@@ -0,0 +1,72 @@
+//! Keys that transactions are signed with, and the places they are loaded
+//! from.
+
+use std::path::PathBuf;
+
+use near_crypto::{InMemorySigner, PublicKey, Signer};
+use near_primitives::{
+    transaction::{SignedTransaction, Transaction},
+    types::AccountId,
+};
+use thiserror::Error;
+
+use self::external::ExternalSigner;
+
+pub mod credentials;
+pub mod external;
+pub mod keystore;
+
+#[derive(Debug, Error)]
+pub enum SignerError {
+    #[error("Could not reach external signer at {path}: {source}")]
+    Connect {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("External signer failed: {0}")]
+    External(String),
+    #[error("External signer returned an invalid signature for {0}")]
+    InvalidSignature(PublicKey),
+}
+
+/// A single access key of the service account.
+#[derive(Clone)]
+pub enum TransactionSigner {
+    /// The secret key is held by this process.
+    InMemory(InMemorySigner),
+    /// The secret key is held by another process.
+    External(ExternalSigner),
+}
+
+impl From<InMemorySigner> for TransactionSigner {
+    fn from(signer: InMemorySigner) -> Self {
+        Self::InMemory(signer)
+    }
+}
+
+impl TransactionSigner {
+    pub fn account_id(&self) -> &AccountId {
+        match self {
+            Self::InMemory(signer) => &signer.account_id,
+            Self::External(signer) => &signer.account_id,
+        }
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        match self {
+            Self::InMemory(signer) => &signer.public_key,
+            Self::External(signer) => &signer.public_key,
+        }
+    }
+
+    pub async fn sign(&self, transaction: Transaction) -> Result<SignedTransaction, SignerError> {
+        let (hash, _) = transaction.get_hash_and_size();
+
+        let signature = match self {
+            Self::InMemory(signer) => signer.sign(hash.as_ref()),
+            Self::External(signer) => signer.sign(&transaction, &hash).await?,
+        };
+
+        Ok(SignedTransaction::new(signature, transaction))
+    }
+}
@@ -0,0 +1,130 @@
+//! Log and trace output.
+
+use std::str::FromStr;
+
+use serde::Deserialize;
+use thiserror::Error;
+use tracing_subscriber::{
+    fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+const DEFAULT_FILTER: &str = "contract_registry_service=info,warp=info";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("expected \"text\" or \"json\"".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// OTLP/HTTP endpoint of an OpenTelemetry collector, e.g.
+    /// `http://localhost:4318/v1/traces`
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum TelemetryError {
+    #[error("Could not set up trace export: {0}")]
+    Export(String),
+    #[error("Could not install the log subscriber: {0}")]
+    Init(#[from] tracing_subscriber::util::TryInitError),
+}
+
+/// Flushes exported traces when dropped; keep it alive until exit.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Could not flush traces: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+fn otel_layer<S>(
+    endpoint: &str,
+) -> Result<(impl Layer<S>, opentelemetry_sdk::trace::SdkTracerProvider), TelemetryError>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .map_err(|e| TelemetryError::Export(e.to_string()))?;
+
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME")));
+
+    Ok((layer, provider))
+}
+
+/// Installs the global subscriber. Logs go to stderr, so that command output
+/// on stdout stays machine-readable. `RUST_LOG` overrides the default filter.
+pub fn init(config: &LoggingConfig) -> Result<TelemetryGuard, TelemetryError> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_span_events(FmtSpan::CLOSE);
+    let fmt = match config.format {
+        LogFormat::Text => fmt.boxed(),
+        LogFormat::Json => fmt.json().with_current_span(true).boxed(),
+    };
+
+    let registry = tracing_subscriber::registry().with(filter).with(fmt);
+
+    #[cfg(feature = "otel")]
+    {
+        let (layer, provider) = match &config.otlp_endpoint {
+            Some(endpoint) => {
+                let (layer, provider) = otel_layer(endpoint)?;
+                (Some(layer), Some(provider))
+            }
+            None => (None, None),
+        };
+        registry.with(layer).try_init()?;
+        Ok(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.try_init()?;
+        Ok(TelemetryGuard {})
+    }
+}
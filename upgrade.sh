@@ -0,0 +1,12 @@
+#!/usr/bin/env bash
+
+# Deploys over an existing registry and migrates its state in the same
+# transaction.
+
+WASM_PATH="$(find ./target/wasm32-unknown-unknown/release/ -maxdepth 1 -name "*.wasm")"
+
+near deploy \
+  --wasmFile $WASM_PATH \
+  --accountId "$1" \
+  --initFunction migrate \
+  --initArgs '{}'